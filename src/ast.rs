@@ -1,5 +1,13 @@
+use std::fmt;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::scanner::token::Token;
-// use std::fmt;
+use crate::scanner::token_type::TokenType;
+
+mod json;
+
+pub use json::program_to_json;
 
 #[derive(Debug)]
 pub enum Stmt {
@@ -7,6 +15,8 @@ pub enum Stmt {
         expr: Expr,
     },
     Print {
+        // Where a failed write is reported, the end of the expression for a REPL echo
+        keyword: Token,
         expr: Expr,
     },
     VarDecl {
@@ -22,11 +32,61 @@ pub enum Stmt {
         else_stmt: Option<Box<Stmt>>,
     },
     While {
+        // The `while` or `for` keyword
+        keyword: Token,
         condition: Expr,
         body: Box<Stmt>,
+        // Evaluated after each iteration, including ones ended by `continue`
+        increment: Option<Expr>,
+    },
+    /// Runs the statements of the first case whose value equals the discriminant by `==`,
+    /// or else those of the default case, if any
+    /// There is no fallthrough, so `break` and `continue` still refer to an enclosing loop
+    Switch {
+        discriminant: Expr,
+        // Each case's statements run in a scope of their own, like a block
+        cases: Vec<(Expr, Vec<Stmt>)>,
+        default: Option<Vec<Stmt>>,
+    },
+    Break {
+        keyword: Token,
+    },
+    Continue {
+        keyword: Token,
+    },
+    Function {
+        decl: Rc<FunctionDecl>,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+    },
+    Class {
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Rc<FunctionDecl>>,
+        // Declared with a leading `class`, and called on the class rather than an instance
+        static_methods: Vec<Rc<FunctionDecl>>,
     },
 }
 
+/// Shared by function declarations, class methods and lambdas
+#[derive(Debug)]
+pub struct FunctionDecl {
+    // The `fun` keyword for lambdas, which have no name
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+    // Methods declared without a parameter list, which run when the property is accessed
+    pub is_getter: bool,
+}
+
+impl FunctionDecl {
+    pub fn is_lambda(&self) -> bool {
+        matches!(self.name.token_type(), TokenType::Fun)
+    }
+}
+
 #[derive(Debug)]
 pub enum Expr {
     Binary {
@@ -38,7 +98,7 @@ pub enum Expr {
         expression: Box<Expr>,
     },
     Literal {
-        value: Token, // TODO: supposed to be Object
+        value: Literal,
     },
     Unary {
         operator: Token,
@@ -46,44 +106,360 @@ pub enum Expr {
     },
     Variable {
         name: Token,
+        id: ExprId,
     },
     Assignment {
         var_name: Token,
         value: Box<Expr>,
+        id: ExprId,
+    },
+    /// `++name`, `name++`, `--name` or `name--`
+    Increment {
+        var_name: Token,
+        // `++` or `--`
+        operator: Token,
+        // Prefix forms evaluate to the new value, postfix ones to the old value
+        prefix: bool,
+        id: ExprId,
     },
     Logical {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
+    /// `condition ? then_branch : else_branch`
+    Ternary {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    This {
+        keyword: Token,
+        id: ExprId,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+        id: ExprId,
+    },
+    /// `fun (params) { body }`
+    Lambda {
+        decl: Rc<FunctionDecl>,
+    },
+    /// `[a, b, c]`
+    ListLiteral {
+        elements: Vec<Expr>,
+    },
+    /// `{key: value, ...}`, errors in keys are reported at the opening brace
+    MapLiteral {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
+    /// `collection[index]`, errors are reported at the closing bracket
+    Index {
+        collection: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    /// `collection[index] = value`
+    IndexSet {
+        collection: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+}
+
+/// Value of a literal, worked out from its token while parsing
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+}
+
+/// Uniquely identifies an expression so later passes can attach information to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ExprId(usize);
+
+impl ExprId {
+    /// Ids are unique across the whole process so that expressions parsed from
+    /// different sources (e.g. REPL lines) never collide
+    pub fn fresh() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Prints statements as S-expressions, e.g. `(var x (+ 1 2))`
+/// Nested statements are put on their own lines, indented by how deeply they are nested
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            Indented {
+                stmt: self,
+                depth: 0
+            }
+        )
+    }
 }
 
-// impl fmt::Display for Expr {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         match self {
-//             Self::Binary {
-//                 left,
-//                 operator,
-//                 right,
-//             } => {
-//                 write!(
-//                     f,
-//                     "\x1b[31m(\x1b[0m{} {} {}\x1b[31m)\x1b[0m",
-//                     left, operator, right
-//                 )
-//             }
-//             Self::Grouping { expression } => {
-//                 write!(f, "\x1b[32m(\x1b[0m{}\x1b[32m)\x1b[0m", expression)
-//             }
-//             Self::Literal { value } => {
-//                 write!(f, "\x1b[34m(\x1b[0m{}\x1b[34m)\x1b[0m", value)
-//             }
-//             Self::Unary { operator, right } => {
-//                 write!(f, "\x1b[36m(\x1b[0m{} {}\x1b[36m)\x1b[0m", operator, right)
-//             }
-//             Self::Variable { name } => {
-//                 write!(f, "{}", name)
-//             }
-//         }
-//     }
-// }
+struct Indented<'a> {
+    stmt: &'a Stmt,
+    depth: usize,
+}
+
+impl fmt::Display for Indented<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let depth = self.depth + 1;
+        match self.stmt {
+            Stmt::Expr { expr } => write!(f, "(expr {expr})"),
+            Stmt::Print { expr, .. } => write!(f, "(print {expr})"),
+            Stmt::VarDecl {
+                var_name,
+                initializer: Some(initializer),
+            } => write!(f, "(var {var_name} {initializer})"),
+            Stmt::VarDecl {
+                var_name,
+                initializer: None,
+            } => write!(f, "(var {var_name})"),
+            Stmt::Block { stmt_list } => {
+                f.write_str("(block")?;
+                for stmt in stmt_list {
+                    write_nested(f, stmt, depth)?;
+                }
+                f.write_str(")")
+            }
+            Stmt::If {
+                condition,
+                then_stmt,
+                else_stmt,
+            } => {
+                write!(f, "(if {condition}")?;
+                write_nested(f, then_stmt, depth)?;
+                if let Some(else_stmt) = else_stmt {
+                    write_nested(f, else_stmt, depth)?;
+                }
+                f.write_str(")")
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment: None,
+                ..
+            } => {
+                write!(f, "(while {condition}")?;
+                write_nested(f, body, depth)?;
+                f.write_str(")")
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment: Some(increment),
+                ..
+            } => {
+                write!(f, "(for {condition} {increment}")?;
+                write_nested(f, body, depth)?;
+                f.write_str(")")
+            }
+            Stmt::Switch {
+                discriminant,
+                cases,
+                default,
+            } => {
+                write!(f, "(switch {discriminant}")?;
+                for (value, stmt_list) in cases {
+                    write_newline(f, depth)?;
+                    write!(f, "(case {value}")?;
+                    for stmt in stmt_list {
+                        write_nested(f, stmt, depth + 1)?;
+                    }
+                    f.write_str(")")?;
+                }
+                if let Some(stmt_list) = default {
+                    write_newline(f, depth)?;
+                    f.write_str("(default")?;
+                    for stmt in stmt_list {
+                        write_nested(f, stmt, depth + 1)?;
+                    }
+                    f.write_str(")")?;
+                }
+                f.write_str(")")
+            }
+            Stmt::Break { .. } => f.write_str("(break)"),
+            Stmt::Continue { .. } => f.write_str("(continue)"),
+            Stmt::Function { decl } => write_function(f, decl, self.depth),
+            Stmt::Return {
+                value: Some(value), ..
+            } => write!(f, "(return {value})"),
+            Stmt::Return { value: None, .. } => f.write_str("(return)"),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                static_methods,
+            } => {
+                write!(f, "(class {name}")?;
+                if let Some(superclass) = superclass {
+                    write!(f, " (< {superclass})")?;
+                }
+                for method in static_methods {
+                    write_newline(f, depth)?;
+                    f.write_str("(static ")?;
+                    write_function(f, method, depth)?;
+                    f.write_str(")")?;
+                }
+                for method in methods {
+                    write_newline(f, depth)?;
+                    write_function(f, method, depth)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+fn write_newline(f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+    write!(f, "\n{:indent$}", "", indent = depth * 2)
+}
+
+/// Writes `stmt` on a line of its own
+fn write_nested(f: &mut fmt::Formatter<'_>, stmt: &Stmt, depth: usize) -> fmt::Result {
+    write_newline(f, depth)?;
+    write!(f, "{}", Indented { stmt, depth })
+}
+
+fn write_function(f: &mut fmt::Formatter<'_>, decl: &FunctionDecl, depth: usize) -> fmt::Result {
+    if decl.is_lambda() {
+        f.write_str("(fun")?;
+    } else if decl.is_getter {
+        write!(f, "(getter {}", decl.name)?;
+    } else {
+        write!(f, "(fun {}", decl.name)?;
+    }
+    // Getters have no parameter list at all
+    if !decl.is_getter {
+        f.write_str(" (")?;
+        for (i, param) in decl.params.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{param}")?;
+        }
+        f.write_str(")")?;
+    }
+    for stmt in &decl.body {
+        write_nested(f, stmt, depth + 1)?;
+    }
+    f.write_str(")")
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nil => f.write_str("nil"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "\"{s}\""),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Self::Logical {
+                left,
+                operator,
+                right,
+            } => write!(f, "({operator} {left} {right})"),
+            Self::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => write!(f, "(? {condition} {then_branch} {else_branch})"),
+            Self::Grouping { expression } => write!(f, "(group {expression})"),
+            Self::Literal { value } => write!(f, "{value}"),
+            Self::Unary { operator, right } => write!(f, "({operator} {right})"),
+            Self::Variable { name, .. } => write!(f, "{name}"),
+            Self::Assignment {
+                var_name, value, ..
+            } => write!(f, "(= {var_name} {value})"),
+            Self::Increment {
+                var_name,
+                operator,
+                prefix: true,
+                ..
+            } => write!(f, "(pre{operator} {var_name})"),
+            Self::Increment {
+                var_name,
+                operator,
+                prefix: false,
+                ..
+            } => write!(f, "(post{operator} {var_name})"),
+            Self::Call {
+                callee, arguments, ..
+            } => {
+                write!(f, "(call {callee}")?;
+                for argument in arguments {
+                    write!(f, " {argument}")?;
+                }
+                f.write_str(")")
+            }
+            Self::Get { object, name } => write!(f, "(get {object} {name})"),
+            Self::Set {
+                object,
+                name,
+                value,
+            } => write!(f, "(set {object} {name} {value})"),
+            Self::This { keyword, .. } => write!(f, "{keyword}"),
+            Self::Super { method, .. } => write!(f, "(super {method})"),
+            Self::Lambda { decl } => write_function(f, decl, 0),
+            Self::ListLiteral { elements } => {
+                f.write_str("(list")?;
+                for element in elements {
+                    write!(f, " {element}")?;
+                }
+                f.write_str(")")
+            }
+            Self::MapLiteral { entries, .. } => {
+                f.write_str("(map")?;
+                for (key, value) in entries {
+                    write!(f, " ({key} {value})")?;
+                }
+                f.write_str(")")
+            }
+            Self::Index {
+                collection, index, ..
+            } => write!(f, "(index {collection} {index})"),
+            Self::IndexSet {
+                collection,
+                index,
+                value,
+                ..
+            } => write!(f, "(set-index {collection} {index} {value})"),
+        }
+    }
+}
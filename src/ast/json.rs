@@ -0,0 +1,370 @@
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+use super::{Expr, FunctionDecl, Literal, Stmt};
+use crate::scanner::token::Token;
+
+/// Serializes a whole program as a JSON array of its statements
+///
+/// ```
+/// use rlox::ast::program_to_json;
+/// use rlox::parser::Parser;
+/// use rlox::scanner::Scanner;
+///
+/// let tokens = Scanner::new("var x = 1 + 2;".to_string()).scan_tokens().unwrap();
+/// let program = Parser::new(tokens).parse().unwrap();
+/// assert_eq!(
+///     program_to_json(&program),
+///     r#"[{"type":"VarDecl","var_name":"x","initializer":{"type":"Binary","operator":"+","#
+///         .to_string()
+///         + r#""left":{"type":"Literal","value":1},"right":{"type":"Literal","value":2}}}]"#
+/// );
+/// ```
+pub fn program_to_json(program: &[Stmt]) -> String {
+    Json::Array(program.iter().map(stmt).collect()).to_string()
+}
+
+impl Stmt {
+    /// Serializes the statement as a JSON object, whose `type` is the name of the variant
+    /// and whose other keys are its fields, with tokens given by their lexemes
+    /// Tokens that are only kept to report errors at, such as a call's paren, are left out
+    pub fn to_json(&self) -> String {
+        stmt(self).to_string()
+    }
+}
+
+impl Expr {
+    /// Serializes the expression the same way as `Stmt::to_json`
+    pub fn to_json(&self) -> String {
+        expr(self).to_string()
+    }
+}
+
+/// A JSON value, with object keys kept in the order they are added
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+/// An object tagged with the name of the AST node it stands for
+fn node(node_type: &str, fields: Vec<(&'static str, Json)>) -> Json {
+    let mut object = vec![("type", Json::String(node_type.to_string()))];
+    object.extend(fields);
+    Json::Object(object)
+}
+
+fn token(token: &Token) -> Json {
+    Json::String(token.lexeme())
+}
+
+fn optional<T>(value: Option<&T>, to_json: impl Fn(&T) -> Json) -> Json {
+    value.map_or(Json::Null, to_json)
+}
+
+fn stmts(stmts: &[Stmt]) -> Json {
+    Json::Array(stmts.iter().map(stmt).collect())
+}
+
+fn exprs(exprs: &[Expr]) -> Json {
+    Json::Array(exprs.iter().map(expr).collect())
+}
+
+fn stmt(stmt: &Stmt) -> Json {
+    match stmt {
+        Stmt::Expr { expr: e } => node("Expr", vec![("expr", expr(e))]),
+        Stmt::Print { expr: e, .. } => node("Print", vec![("expr", expr(e))]),
+        Stmt::VarDecl {
+            var_name,
+            initializer,
+        } => node(
+            "VarDecl",
+            vec![
+                ("var_name", token(var_name)),
+                ("initializer", optional(initializer.as_ref(), expr)),
+            ],
+        ),
+        Stmt::Block { stmt_list } => node("Block", vec![("stmt_list", stmts(stmt_list))]),
+        Stmt::If {
+            condition,
+            then_stmt,
+            else_stmt,
+        } => node(
+            "If",
+            vec![
+                ("condition", expr(condition)),
+                ("then_stmt", self::stmt(then_stmt)),
+                ("else_stmt", optional(else_stmt.as_deref(), self::stmt)),
+            ],
+        ),
+        Stmt::While {
+            condition,
+            body,
+            increment,
+            ..
+        } => node(
+            "While",
+            vec![
+                ("condition", expr(condition)),
+                ("body", self::stmt(body)),
+                ("increment", optional(increment.as_ref(), expr)),
+            ],
+        ),
+        Stmt::Switch {
+            discriminant,
+            cases,
+            default,
+        } => {
+            let cases = cases
+                .iter()
+                .map(|(value, stmt_list)| {
+                    Json::Object(vec![("value", expr(value)), ("stmts", stmts(stmt_list))])
+                })
+                .collect();
+            node(
+                "Switch",
+                vec![
+                    ("discriminant", expr(discriminant)),
+                    ("cases", Json::Array(cases)),
+                    ("default", optional(default.as_ref(), |d| stmts(d))),
+                ],
+            )
+        }
+        Stmt::Break { .. } => node("Break", Vec::new()),
+        Stmt::Continue { .. } => node("Continue", Vec::new()),
+        Stmt::Function { decl } => function("Function", decl),
+        Stmt::Return { value, .. } => {
+            node("Return", vec![("value", optional(value.as_ref(), expr))])
+        }
+        Stmt::Class {
+            name,
+            superclass,
+            methods,
+            static_methods,
+        } => {
+            let functions = |decls: &[Rc<FunctionDecl>]| {
+                Json::Array(
+                    decls
+                        .iter()
+                        .map(|decl| function("Function", decl))
+                        .collect(),
+                )
+            };
+            node(
+                "Class",
+                vec![
+                    ("name", token(name)),
+                    ("superclass", optional(superclass.as_ref(), expr)),
+                    ("methods", functions(methods)),
+                    ("static_methods", functions(static_methods)),
+                ],
+            )
+        }
+    }
+}
+
+/// Lambdas have no name, since their name token is just the `fun` keyword
+fn function(node_type: &str, decl: &FunctionDecl) -> Json {
+    let mut fields = Vec::new();
+    if !decl.is_lambda() {
+        fields.push(("name", token(&decl.name)));
+    }
+    fields.push((
+        "params",
+        Json::Array(decl.params.iter().map(token).collect()),
+    ));
+    fields.push(("body", stmts(&decl.body)));
+    fields.push(("is_getter", Json::Bool(decl.is_getter)));
+    node(node_type, fields)
+}
+
+fn expr(expr: &Expr) -> Json {
+    match expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => node(
+            "Binary",
+            vec![
+                ("operator", token(operator)),
+                ("left", self::expr(left)),
+                ("right", self::expr(right)),
+            ],
+        ),
+        Expr::Grouping { expression } => {
+            node("Grouping", vec![("expression", self::expr(expression))])
+        }
+        Expr::Literal { value } => node("Literal", vec![("value", literal(value))]),
+        Expr::Unary { operator, right } => node(
+            "Unary",
+            vec![("operator", token(operator)), ("right", self::expr(right))],
+        ),
+        Expr::Variable { name, .. } => node("Variable", vec![("name", token(name))]),
+        Expr::Assignment {
+            var_name, value, ..
+        } => node(
+            "Assignment",
+            vec![("var_name", token(var_name)), ("value", self::expr(value))],
+        ),
+        Expr::Increment {
+            var_name,
+            operator,
+            prefix,
+            ..
+        } => node(
+            "Increment",
+            vec![
+                ("var_name", token(var_name)),
+                ("operator", token(operator)),
+                ("prefix", Json::Bool(*prefix)),
+            ],
+        ),
+        Expr::Logical {
+            left,
+            operator,
+            right,
+        } => node(
+            "Logical",
+            vec![
+                ("operator", token(operator)),
+                ("left", self::expr(left)),
+                ("right", self::expr(right)),
+            ],
+        ),
+        Expr::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        } => node(
+            "Ternary",
+            vec![
+                ("condition", self::expr(condition)),
+                ("then_branch", self::expr(then_branch)),
+                ("else_branch", self::expr(else_branch)),
+            ],
+        ),
+        Expr::Call {
+            callee, arguments, ..
+        } => node(
+            "Call",
+            vec![
+                ("callee", self::expr(callee)),
+                ("arguments", exprs(arguments)),
+            ],
+        ),
+        Expr::Get { object, name } => node(
+            "Get",
+            vec![("object", self::expr(object)), ("name", token(name))],
+        ),
+        Expr::Set {
+            object,
+            name,
+            value,
+        } => node(
+            "Set",
+            vec![
+                ("object", self::expr(object)),
+                ("name", token(name)),
+                ("value", self::expr(value)),
+            ],
+        ),
+        Expr::This { .. } => node("This", Vec::new()),
+        Expr::Super { method, .. } => node("Super", vec![("method", token(method))]),
+        Expr::Lambda { decl } => function("Lambda", decl),
+        Expr::ListLiteral { elements } => node("ListLiteral", vec![("elements", exprs(elements))]),
+        Expr::MapLiteral { entries, .. } => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| {
+                    Json::Object(vec![("key", self::expr(key)), ("value", self::expr(value))])
+                })
+                .collect();
+            node("MapLiteral", vec![("entries", Json::Array(entries))])
+        }
+        Expr::Index {
+            collection, index, ..
+        } => node(
+            "Index",
+            vec![
+                ("collection", self::expr(collection)),
+                ("index", self::expr(index)),
+            ],
+        ),
+        Expr::IndexSet {
+            collection,
+            index,
+            value,
+            ..
+        } => node(
+            "IndexSet",
+            vec![
+                ("collection", self::expr(collection)),
+                ("index", self::expr(index)),
+                ("value", self::expr(value)),
+            ],
+        ),
+    }
+}
+
+fn literal(literal: &Literal) -> Json {
+    match literal {
+        Literal::Nil => Json::Null,
+        Literal::Bool(b) => Json::Bool(*b),
+        Literal::Number(n) => Json::Number(*n),
+        Literal::String(s) => Json::String(s.to_string()),
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(b) => write!(f, "{b}"),
+            // JSON has no infinity, which a literal with a huge number of digits parses to
+            Self::Number(n) if !n.is_finite() => f.write_str("null"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write_string(f, s),
+            Self::Array(elements) => {
+                f.write_char('[')?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
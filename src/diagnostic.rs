@@ -0,0 +1,141 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::interpreter::RuntimeError;
+use crate::parser::ParserError;
+use crate::resolver::{ResolverError, ResolverWarning};
+use crate::scanner::ScanError;
+
+/// Location of a piece of source code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    // 1-based, counted in characters from the start of the line
+    pub column: usize,
+    // In characters, 0 for positions such as the end of the source
+    pub len: usize,
+    // Byte offset from the start of the source
+    pub start: usize,
+    // In bytes, so it differs from `len` after multi-byte characters
+    pub byte_len: usize,
+}
+
+impl Span {
+    /// Byte range of the span in its source, so `&source[span.byte_range()]` is its text
+    ///
+    /// ```
+    /// use rlox::scanner::Scanner;
+    ///
+    /// let source = "print \"é\" + 1.5;";
+    /// let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+    /// for token in &tokens {
+    ///     assert_eq!(&source[token.span().byte_range()], token.lexeme());
+    /// }
+    /// assert_eq!(tokens[3].span().byte_range(), 13..16);
+    /// ```
+    pub fn byte_range(&self) -> Range<usize> {
+        self.start..self.start + self.byte_len
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// An error or warning about a program, in a form tools can inspect and render themselves
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Renders the diagnostic with the offending line of `source` and the span underlined
+    ///
+    /// ```
+    /// use rlox::diagnostic::{Diagnostic, Span};
+    ///
+    /// let span = Span { line: 1, column: 9, len: 1, start: 8, byte_len: 1 };
+    /// let rendered = Diagnostic::error("Expect ')'", span).render("print (1;");
+    /// assert_eq!(
+    ///     rendered,
+    ///     "error: Expect ')'\n --> line 1:9\n  |\n1 | print (1;\n  |         ^"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let Span {
+            line, column, len, ..
+        } = self.span;
+        // Positions past the last line, like the end of the source, show an empty line
+        let text = source.lines().nth(line.saturating_sub(1)).unwrap_or("");
+        let gutter = " ".repeat(line.to_string().len());
+
+        let mut rendered = String::new();
+        // Writing into a String can't fail
+        let _ = writeln!(rendered, "{severity}: {}", self.message);
+        let _ = writeln!(rendered, "{gutter}--> line {line}:{column}");
+        let _ = writeln!(rendered, "{gutter} |");
+        let _ = writeln!(rendered, "{line} | {text}");
+        let _ = write!(
+            rendered,
+            "{gutter} | {:indent$}{}",
+            "",
+            "^".repeat(len.max(1)),
+            indent = column.saturating_sub(1)
+        );
+        rendered
+    }
+}
+
+impl From<ScanError> for Diagnostic {
+    fn from(err: ScanError) -> Self {
+        Self::error(err.message, err.span)
+    }
+}
+
+impl From<ParserError> for Diagnostic {
+    fn from(err: ParserError) -> Self {
+        Self::error(err.message(), err.token().span())
+    }
+}
+
+impl From<ResolverError> for Diagnostic {
+    fn from(err: ResolverError) -> Self {
+        Self::error(err.message(), err.token().span())
+    }
+}
+
+impl From<ResolverWarning> for Diagnostic {
+    fn from(warning: ResolverWarning) -> Self {
+        Self::warning(warning.message(), warning.token().span())
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(err: RuntimeError) -> Self {
+        Self::error(err.message(), err.token().span())
+    }
+}
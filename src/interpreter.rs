@@ -1,139 +1,565 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-use crate::ast::{Expr, Stmt};
+use crate::ast::{Expr, ExprId, FunctionDecl, Literal, Stmt};
 use crate::scanner::token::Token;
 use crate::scanner::token_type::TokenType;
+use environment::EnvironmentList;
+use lox_value::{LoxClass, LoxFunction, LoxInstance, MapKey, NativeFn};
 
-pub struct Interpreter {
-    // lox: &'a mut Lox,
-    env_list: EnvironmentList,
-    errors: Vec<RuntimeError>,
-}
+pub use lox_value::LoxValue;
 
-struct EnvironmentList {
-    env_list: Vec<Environment>,
-}
+mod environment;
+mod lox_value;
+mod native;
 
-struct Environment {
-    map: HashMap<String, LoxValue>,
-}
+/// Shallow enough to fit the 2MB stack of a spawned thread even in a debug build,
+/// where a Lox call takes around 10 to 20KB of native stack
+/// main.rs raises it, since it runs the interpreter on a much larger stack
+const DEFAULT_MAX_CALL_DEPTH: usize = 64;
 
-#[derive(Clone, Debug, PartialEq)]
-enum LoxValue {
-    Nil,
-    Bool(bool),
-    Number(f64),
-    String(String),
+/// Longest string, in bytes, that repeating a string with `*` may produce
+const MAX_REPEATED_LEN: usize = 1 << 28;
+
+pub struct Interpreter {
+    env_list: EnvironmentList,
+    // For each resolved local variable, the number of scopes between it and its declaration,
+    // and its slot in the declaring scope
+    locals: HashMap<ExprId, (usize, usize)>,
+    // Where `print` statements and REPL echoes are written to
+    // Shared with the `read_line` native
+    input: Rc<RefCell<Box<dyn Read>>>,
+    // Shared with the `write` native
+    out: Rc<RefCell<Box<dyn Write>>>,
+    // Number of Lox function calls currently running
+    call_depth: usize,
+    max_call_depth: usize,
+    // Number of statements executed and expressions evaluated so far
+    steps: usize,
+    max_steps: Option<usize>,
+    // Set by the host, possibly from another thread, to stop the running script
+    cancelled: Arc<AtomicBool>,
 }
 
 #[derive(Clone, Debug)]
 pub enum RuntimeError {
-    InvalidBinaryOperand(Token),
+    // The type names of the left and right operands
+    InvalidBinaryOperand(Token, &'static str, &'static str),
     InvalidUnaryOperand(Token),
-    UnexpectedLiteralTokenType(Token),
     UndefinedVariable(Token),
+    NotCallable(Token),
+    // The expected and actual number of arguments
+    ArityMismatch(Token, usize, usize),
+    OnlyInstancesHaveProperties(Token),
+    OnlyInstancesHaveFields(Token),
+    UndefinedProperty(Token),
+    SuperclassMustBeClass(Token),
+    DivisionByZero(Token),
+    StringTooLong(Token),
+    StackOverflow(Token),
+    BudgetExceeded(Token),
+    Cancelled(Token),
+    NotIndexable(Token),
+    InvalidMapKey(Token),
+    InvalidIndex(Token),
+    IndexOutOfBounds(Token),
+    // Built by `invalid_argument`, naming the native, what it expects and what it got instead
+    InvalidArgument(Token, String),
+    SqrtOfNegative(Token),
+    PopFromEmptyList(Token),
+    InvalidNumber(Token),
+    ReadFailed(Token),
+    WriteFailed(Token),
+    AssertionFailed(Token),
+    // The two values that were expected to be equal
+    AssertionNotEqual(Token, LoxValue, LoxValue),
+}
+
+/// Reasons for a statement to stop executing before it completes
+enum Unwind {
+    Error(RuntimeError),
+    Return(LoxValue),
+    Break,
+    Continue,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {
-            // lox,
+        Self::with_writer(Box::new(io::stdout()))
+    }
+
+    /// Creates an interpreter whose output goes to `out` instead of stdout
+    pub fn with_writer(out: Box<dyn Write>) -> Self {
+        Self::with_io(Box::new(io::stdin()), out)
+    }
+
+    /// Creates an interpreter that reads input for `read_line` from `input`
+    /// and writes output to `out`
+    pub fn with_io(input: Box<dyn Read>, out: Box<dyn Write>) -> Self {
+        let mut interpreter = Self {
             env_list: EnvironmentList::new(),
-            errors: Vec::new(),
+            locals: HashMap::new(),
+            input: Rc::new(RefCell::new(input)),
+            out: Rc::new(RefCell::new(out)),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            steps: 0,
+            max_steps: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+        interpreter.define_natives();
+        interpreter
+    }
+
+    /// Forgets every variable, function and class defined so far, as if just created
+    /// The built-in natives are defined again, but ones added with `define_native` are not
+    pub fn reset(&mut self) {
+        self.env_list = EnvironmentList::new();
+        self.locals.clear();
+        self.call_depth = 0;
+        self.steps = 0;
+        self.define_natives();
+    }
+
+    /// Defines the built-in natives as globals, e.g. `clock`, `len`, `sqrt` and `min`
+    /// Their names are taken before a script starts, though a script may redefine them
+    fn define_natives(&mut self) {
+        self.define_native("clock", 0, native::clock);
+        self.define_native("len", 1, native::len);
+        self.define_native("push", 2, native::push);
+        self.define_native("pop", 1, native::pop);
+        self.define_native("type", 1, native::type_of);
+        self.define_native("number", 1, native::number);
+        self.define_builtin("str", 1, native::str);
+        self.define_native("fmt", 2, native::fmt);
+        self.define_native("assert", 1, native::assert);
+        self.define_native("assert_eq", 2, native::assert_eq);
+        self.define_native("abs", 1, native::abs);
+        self.define_native("floor", 1, native::floor);
+        self.define_native("ceil", 1, native::ceil);
+        self.define_native("sqrt", 1, native::sqrt);
+        self.define_native("min", 2, native::min);
+        self.define_native("max", 2, native::max);
+        let input = Rc::clone(&self.input);
+        self.define_native("read_line", 0, move |paren, _| {
+            native::read_line(paren, &mut *input.borrow_mut())
+        });
+        self.define_builtin("write", 1, native::write);
+    }
+
+    /// Defines a global function implemented in Rust, callable from scripts as `name`
+    /// Calls with a number of arguments other than `arity` raise a runtime error
+    /// before `fun` is invoked
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        fun: impl Fn(&Token, &[LoxValue]) -> Result<LoxValue, RuntimeError> + 'static,
+    ) {
+        self.define_builtin(name, arity, move |_, paren, arguments| {
+            fun(paren, arguments)
+        });
+    }
+
+    /// Like `define_native`, but `fun` is also given the interpreter,
+    /// for natives that call back into Lox code such as a `toString` method
+    fn define_builtin(
+        &mut self,
+        name: &str,
+        arity: usize,
+        fun: impl Fn(&mut Interpreter, &Token, &[LoxValue]) -> Result<LoxValue, RuntimeError> + 'static,
+    ) {
+        let native = NativeFn {
+            name: name.to_string(),
+            arity,
+            fun: Box::new(fun),
+        };
+        self.env_list
+            .declare_var(name, LoxValue::NativeFn(Rc::new(native)));
+    }
+
+    /// Calls nested deeper than `max_call_depth` raise a stack overflow error
+    /// instead of overflowing the native stack
+    /// The default of 64 fits a 2MB stack, and hosts with a larger one can raise it
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Once more than `max_steps` statements and expressions have been run in total,
+    /// the next loop iteration or call raises an error, so that runaway scripts are stopped
+    /// `None`, the default, means there is no limit
+    ///
+    /// ```
+    /// use rlox::{Interpreter, LoxError, RuntimeError};
+    ///
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter.set_max_steps(Some(1000));
+    /// let result = rlox::run_source(&mut interpreter, "while (true) {}");
+    /// assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::BudgetExceeded(_)))));
+    /// ```
+    pub fn set_max_steps(&mut self, max_steps: Option<usize>) {
+        self.max_steps = max_steps;
+    }
+
+    /// A flag that stops the running script with a `Cancelled` error at its next loop
+    /// iteration or call once set, e.g. by another thread on a timer
+    /// The flag stays set, so it has to be cleared before running anything else
+    ///
+    /// ```
+    /// use std::sync::atomic::Ordering;
+    /// use std::{thread, time::Duration};
+    ///
+    /// use rlox::{Interpreter, LoxError, RuntimeError};
+    ///
+    /// let mut interpreter = Interpreter::new();
+    /// let cancelled = interpreter.cancellation_token();
+    /// let timer = thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(50));
+    ///     cancelled.store(true, Ordering::Relaxed);
+    /// });
+    /// let result = rlox::run_source(&mut interpreter, "while (true) {}");
+    /// assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::Cancelled(_)))));
+    /// timer.join().unwrap();
+    /// ```
+    pub fn cancellation_token(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.cancelled)
+    }
+
+    /// Checked at each loop iteration and call, which any long-running script has to go through
+    fn check_limits(&self, token: &Token) -> Result<(), RuntimeError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(RuntimeError::Cancelled(token.clone()));
         }
+        match self.max_steps {
+            Some(max_steps) if self.steps > max_steps => {
+                Err(RuntimeError::BudgetExceeded(token.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn resolve(&mut self, id: ExprId, depth: usize, slot: usize) {
+        self.locals.insert(id, (depth, slot));
     }
 
-    pub fn interpret(&mut self, program: Vec<Stmt>) -> Result<(), Vec<RuntimeError>> {
+    /// Stops at the first runtime error, like the reference Lox implementation
+    pub fn interpret(&mut self, program: Vec<Stmt>) -> Result<(), RuntimeError> {
         for stmt in program {
-            if let Err(err) = self.execute(&stmt) {
-                // self.lox.runtime_error(err);
-                self.errors.push(err);
+            if let Err(Unwind::Error(err)) = self.execute(&stmt) {
+                return Err(err);
             }
         }
+        Ok(())
+    }
 
-        // TODO: Weird handling of errors
-        //       clone is unnecessary if Lox just access the field directly
-        self.errors
-            .is_empty()
-            .then(|| ())
-            .ok_or(self.errors.clone())
+    /// Writes the value as `print` shows it, on a line of its own
+    fn write_line(&mut self, val: LoxValue, keyword: &Token) -> Result<(), RuntimeError> {
+        let val = self.call_to_string(val)?;
+        writeln!(self.out.borrow_mut(), "{val}")
+            .map_err(|_| RuntimeError::WriteFailed(keyword.clone()))
     }
 
-    fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
-        match stmt {
-            Stmt::Expr { expr } => {
-                self.evaluate(expr)?;
-                // let val = self.evaluate(expr)?;
-                // println!("expr = {val}");
-            }
-            Stmt::Print { expr } => {
-                let val = self.evaluate(expr)?;
-                println!("{val}");
+    /// Returns what the instance's `toString` method returns, if its class has one taking
+    /// no arguments, and any other value unchanged
+    /// Used wherever a value is turned into text, i.e. `print`, `str` and `write`
+    /// Only the value itself is affected, not instances inside a list or map
+    fn call_to_string(&mut self, val: LoxValue) -> Result<LoxValue, RuntimeError> {
+        let LoxValue::Instance(instance) = &val else {
+            return Ok(val);
+        };
+        let method = instance.borrow().class.find_method("toString");
+        match method {
+            Some(method) if method.arity() == 0 => {
+                let method = method.bind(Rc::clone(instance));
+                // There is no call paren, so errors like stack overflow point at the method
+                let name = method.declaration.name.clone();
+                self.call_function(&method, &name, Vec::new())
             }
+            _ => Ok(val),
+        }
+    }
+
+    /// Runs the statements in a new scope
+    fn execute_block(&mut self, stmt_list: &[Stmt]) -> Result<(), Unwind> {
+        self.env_list.push_new_env();
+
+        // The scope is popped however the block ends, errors included
+        let result = stmt_list.iter().try_for_each(|stmt| self.execute(stmt));
+        self.env_list.pop_env();
+        result
+    }
+
+    /// Most statements are run by a function of their own, as is done in `evaluate`,
+    /// since this function's frame is on the native stack once per nested statement
+    /// and a debug build doesn't share stack slots between match arms
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        self.steps += 1;
+        match stmt {
+            Stmt::Expr { expr } => self.execute_expr(expr),
+            Stmt::Print { keyword, expr } => self.execute_print(keyword, expr),
             Stmt::VarDecl {
                 var_name,
                 initializer,
-            } => {
-                let init_val = initializer
-                    .as_ref()
-                    .map(|i| self.evaluate(i))
-                    .unwrap_or(Ok(LoxValue::Nil))?;
-                self.env_list.declare_var(&var_name.lexeme(), init_val);
-            }
-            Stmt::Block { stmt_list } => {
-                self.env_list.push_new_env();
-
-                for stmt in stmt_list {
-                    if let Err(err) = self.execute(&stmt) {
-                        self.errors.push(err);
-                    }
-                }
-
-                self.env_list.pop_env();
-            }
+            } => self.execute_var_decl(var_name, initializer.as_ref()),
+            Stmt::Block { stmt_list } => self.execute_block(stmt_list),
+            Stmt::Switch {
+                discriminant,
+                cases,
+                default,
+            } => self.execute_switch(discriminant, cases, default.as_deref()),
             Stmt::If {
                 condition,
                 then_stmt,
                 else_stmt,
-            } => {
-                if self.evaluate(condition)?.truthiness() {
-                    self.execute(&then_stmt)?
-                } else if let Some(else_stmt) = else_stmt {
-                    self.execute(&else_stmt)?
-                }
+            } => self.execute_if(condition, then_stmt, else_stmt.as_deref()),
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+                increment,
+            } => self.execute_while(keyword, condition, body, increment.as_ref()),
+            Stmt::Break { .. } => Err(Unwind::Break),
+            Stmt::Continue { .. } => Err(Unwind::Continue),
+            Stmt::Function { decl } => {
+                let function = self.evaluate_lambda(decl);
+                self.env_list.declare_var(&decl.name.lexeme(), function);
+                Ok(())
             }
-            Stmt::While { condition, body } => {
-                while self.evaluate(condition)?.truthiness() {
-                    self.execute(&body)?
-                }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                static_methods,
+            } => self.execute_class(name, superclass.as_ref(), methods, static_methods),
+            Stmt::Return { value, .. } => self.execute_return(value.as_ref()),
+        }
+    }
+
+    fn execute_expr(&mut self, expr: &Expr) -> Result<(), Unwind> {
+        self.evaluate(expr)?;
+        Ok(())
+    }
+
+    fn execute_print(&mut self, keyword: &Token, expr: &Expr) -> Result<(), Unwind> {
+        let val = self.evaluate(expr)?;
+        Ok(self.write_line(val, keyword)?)
+    }
+
+    fn execute_var_decl(
+        &mut self,
+        var_name: &Token,
+        initializer: Option<&Expr>,
+    ) -> Result<(), Unwind> {
+        let init_val = match initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => LoxValue::Nil,
+        };
+        self.env_list.declare_var(&var_name.lexeme(), init_val);
+        Ok(())
+    }
+
+    fn execute_if(
+        &mut self,
+        condition: &Expr,
+        then_stmt: &Stmt,
+        else_stmt: Option<&Stmt>,
+    ) -> Result<(), Unwind> {
+        if self.evaluate(condition)?.truthiness() {
+            self.execute(then_stmt)
+        } else if let Some(else_stmt) = else_stmt {
+            self.execute(else_stmt)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn execute_return(&mut self, value: Option<&Expr>) -> Result<(), Unwind> {
+        let value = match value {
+            Some(value) => self.evaluate(value)?,
+            None => LoxValue::Nil,
+        };
+        Err(Unwind::Return(value))
+    }
+
+    fn execute_switch(
+        &mut self,
+        discriminant: &Expr,
+        cases: &[(Expr, Vec<Stmt>)],
+        default: Option<&[Stmt]>,
+    ) -> Result<(), Unwind> {
+        let discriminant = self.evaluate(discriminant)?;
+        let mut matched = None;
+        for (value, stmt_list) in cases {
+            if self.evaluate(value)? == discriminant {
+                matched = Some(stmt_list.as_slice());
+                break;
+            }
+        }
+        match matched.or(default) {
+            Some(stmt_list) => self.execute_block(stmt_list),
+            None => Ok(()),
+        }
+    }
+
+    fn execute_while(
+        &mut self,
+        keyword: &Token,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> Result<(), Unwind> {
+        while self.evaluate(condition)?.truthiness() {
+            self.check_limits(keyword)?;
+            match self.execute(body) {
+                Ok(()) | Err(Unwind::Continue) => (),
+                Err(Unwind::Break) => break,
+                Err(unwind) => return Err(unwind),
+            }
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
             }
         }
         Ok(())
     }
 
+    fn execute_class(
+        &mut self,
+        name: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Rc<FunctionDecl>],
+        static_methods: &[Rc<FunctionDecl>],
+    ) -> Result<(), Unwind> {
+        let superclass = match superclass {
+            Some(superclass) => match self.evaluate(superclass)? {
+                LoxValue::Class(class) => Some(class),
+                _ => return Err(RuntimeError::SuperclassMustBeClass(name.clone()).into()),
+            },
+            None => None,
+        };
+
+        if let Some(superclass) = &superclass {
+            self.env_list.push_new_env();
+            self.env_list
+                .declare_var("super", LoxValue::Class(Rc::clone(superclass)));
+        }
+
+        let methods = methods
+            .iter()
+            .map(|method| {
+                let function = LoxFunction {
+                    declaration: Rc::clone(method),
+                    closure: self.env_list.clone(),
+                    is_initializer: method.name.lexeme() == "init",
+                };
+                (method.name.lexeme(), Rc::new(function))
+            })
+            .collect();
+
+        if superclass.is_some() {
+            self.env_list.pop_env();
+        }
+
+        let static_methods = static_methods
+            .iter()
+            .map(|method| {
+                let function = LoxFunction {
+                    declaration: Rc::clone(method),
+                    closure: self.env_list.clone(),
+                    is_initializer: false,
+                };
+                (method.name.lexeme(), Rc::new(function))
+            })
+            .collect();
+
+        let class = LoxClass {
+            name: name.lexeme(),
+            superclass,
+            methods,
+            static_methods,
+        };
+        self.env_list
+            .declare_var(&name.lexeme(), LoxValue::Class(Rc::new(class)));
+        Ok(())
+    }
+
     fn evaluate(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
-        let val = match expr {
+        self.steps += 1;
+        match expr {
             Expr::Binary {
                 left,
                 operator,
                 right,
             } => self.evaluate_binary(left, operator, right),
-            Expr::Grouping { expression } => self.evaluate(&expression),
-            Expr::Literal { value } => self.evaluate_literal(value),
+            Expr::Grouping { expression } => self.evaluate(expression),
+            Expr::Literal { value } => Ok(Self::evaluate_literal(value)),
             Expr::Unary { operator, right } => self.evaluate_unary(operator, right),
-            Expr::Variable { name } => self.evaluate_var(name),
-            Expr::Assignment { var_name, value } => {
-                let value = self.evaluate(value)?;
-                self.evaluate_assignment(var_name, value)
-            }
+            Expr::Variable { name, id } => self.evaluate_var(*id, name),
+            Expr::Assignment {
+                var_name,
+                value,
+                id,
+            } => self
+                .evaluate(value)
+                .and_then(|value| self.evaluate_assignment(*id, var_name, value)),
+            Expr::Increment {
+                var_name,
+                operator,
+                prefix,
+                id,
+            } => self.evaluate_increment(*id, var_name, operator, *prefix),
             Expr::Logical {
                 left,
                 operator,
                 right,
             } => self.evaluate_logical(left, operator, right),
-        };
-        val
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.evaluate_ternary(condition, then_branch, else_branch),
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => self.evaluate_call(callee, paren, arguments),
+            Expr::Get { object, name } => self.evaluate_get(object, name),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => self.evaluate_set(object, name, value),
+            Expr::This { keyword, id } => self.evaluate_var(*id, keyword),
+            Expr::Super {
+                keyword,
+                method,
+                id,
+            } => self.evaluate_super(*id, keyword, method),
+            Expr::Lambda { decl } => Ok(self.evaluate_lambda(decl)),
+            Expr::ListLiteral { elements } => self.evaluate_list_literal(elements),
+            Expr::MapLiteral { brace, entries } => self.evaluate_map_literal(brace, entries),
+            Expr::Index {
+                collection,
+                bracket,
+                index,
+            } => self.evaluate_index(collection, bracket, index),
+            Expr::IndexSet {
+                collection,
+                bracket,
+                index,
+                value,
+            } => self.evaluate_index_set(collection, bracket, index, value),
+        }
     }
 
     fn evaluate_binary(
@@ -144,33 +570,90 @@ impl Interpreter {
     ) -> Result<LoxValue, RuntimeError> {
         let left = self.evaluate(left)?;
         let right = self.evaluate(right)?;
+        Self::apply_binary(operator, left, right)
+    }
+
+    /// Kept apart from `evaluate_binary`, so its frame isn't on the stack while operands,
+    /// which may call functions, are evaluated
+    fn apply_binary(
+        operator: &Token,
+        left: LoxValue,
+        right: LoxValue,
+    ) -> Result<LoxValue, RuntimeError> {
+        let types = (left.type_name(), right.type_name());
+        let invalid_operands =
+            || RuntimeError::InvalidBinaryOperand(operator.clone(), types.0, types.1);
         let result = match operator.token_type() {
             TokenType::Plus => Self::plus(left, right),
             TokenType::Minus => Self::minus(left, right),
+            TokenType::Star if Self::repeats_too_long(&left, &right) => {
+                return Err(RuntimeError::StringTooLong(operator.clone()))
+            }
             TokenType::Star => Self::multiply(left, right),
+            TokenType::Slash | TokenType::Percent if Self::divides_by_zero(&left, &right) => {
+                return Err(RuntimeError::DivisionByZero(operator.clone()))
+            }
             TokenType::Slash => Self::divide(left, right),
+            TokenType::Percent => Self::modulo(left, right),
             TokenType::BangEqual => Ok(Self::not_equal(left, right)),
             TokenType::EqualEqual => Ok(Self::equal(left, right)),
             TokenType::Greater => Self::greater(left, right),
             TokenType::GreaterEqual => Self::greater_equal(left, right),
             TokenType::Less => Self::less(left, right),
             TokenType::LessEqual => Self::less_equal(left, right),
-            _ => return Err(RuntimeError::InvalidBinaryOperand(operator.clone())),
+            TokenType::Ampersand => Self::bitwise(left, right, |l, r| Some(l & r)),
+            TokenType::Pipe => Self::bitwise(left, right, |l, r| Some(l | r)),
+            TokenType::Caret => Self::bitwise(left, right, |l, r| Some(l ^ r)),
+            TokenType::LessLess => {
+                Self::bitwise(left, right, |l, r| l.checked_shl(r.try_into().ok()?))
+            }
+            TokenType::GreaterGreater => {
+                Self::bitwise(left, right, |l, r| l.checked_shr(r.try_into().ok()?))
+            }
+            _ => return Err(invalid_operands()),
         };
-        result.map_err(|_| RuntimeError::InvalidBinaryOperand(operator.clone()))
+        result.map_err(|_| invalid_operands())
+    }
+
+    fn evaluate_literal(literal: &Literal) -> LoxValue {
+        match literal {
+            Literal::Nil => LoxValue::Nil,
+            Literal::Bool(b) => LoxValue::Bool(*b),
+            Literal::Number(n) => LoxValue::Number(*n),
+            Literal::String(s) => LoxValue::String(Rc::clone(s)),
+        }
     }
 
-    fn evaluate_literal(&self, token: &Token) -> Result<LoxValue, RuntimeError> {
-        match token.token_type() {
-            TokenType::Nil => Ok(LoxValue::Nil),
-            TokenType::True => Ok(LoxValue::Bool(true)),
-            TokenType::False => Ok(LoxValue::Bool(false)),
-            TokenType::Number(s) => Ok(LoxValue::Number(s)),
-            TokenType::String(s) => Ok(LoxValue::String(s)),
-            _ => Err(RuntimeError::UnexpectedLiteralTokenType(token.clone())),
+    fn evaluate_ternary(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Result<LoxValue, RuntimeError> {
+        if self.evaluate(condition)?.truthiness() {
+            self.evaluate(then_branch)
+        } else {
+            self.evaluate(else_branch)
         }
     }
 
+    fn evaluate_lambda(&self, decl: &Rc<FunctionDecl>) -> LoxValue {
+        let function = LoxFunction {
+            declaration: Rc::clone(decl),
+            closure: self.env_list.clone(),
+            is_initializer: false,
+        };
+        LoxValue::Function(Rc::new(function))
+    }
+
+    fn evaluate_list_literal(&mut self, elements: &[Expr]) -> Result<LoxValue, RuntimeError> {
+        let elements = elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<_, _>>()?;
+        Ok(LoxValue::List(Rc::new(RefCell::new(elements))))
+    }
+
     fn evaluate_unary(&mut self, operator: &Token, right: &Expr) -> Result<LoxValue, RuntimeError> {
         let right = self.evaluate(right)?;
         match operator.token_type() {
@@ -182,26 +665,55 @@ impl Interpreter {
                     Err(RuntimeError::InvalidUnaryOperand(operator.clone()))
                 }
             }
-            _ => Err(RuntimeError::InvalidBinaryOperand(operator.clone())),
+            TokenType::Tilde => Self::integer(&right)
+                .map(|n| LoxValue::Number(!n as f64))
+                .map_err(|_| RuntimeError::InvalidUnaryOperand(operator.clone())),
+            _ => Err(RuntimeError::InvalidUnaryOperand(operator.clone())),
         }
     }
 
-    fn evaluate_var(&self, var: &Token) -> Result<LoxValue, RuntimeError> {
-        self.env_list
-            .get_var(&var.lexeme())
-            .map_err(|_| RuntimeError::UndefinedVariable(var.to_owned()))
+    fn evaluate_var(&self, id: ExprId, var: &Token) -> Result<LoxValue, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(&(depth, slot)) => self.env_list.get_var_at(depth, slot),
+            None => self.env_list.get_global(&var.lexeme()),
+        }
+        .map_err(|_| RuntimeError::UndefinedVariable(var.to_owned()))
     }
 
     fn evaluate_assignment(
         &mut self,
+        id: ExprId,
         var: &Token,
         value: LoxValue,
     ) -> Result<LoxValue, RuntimeError> {
-        self.env_list
-            .set_var(&var.lexeme(), value)
-            .map_err(|_| RuntimeError::UndefinedVariable(var.to_owned()))
+        match self.locals.get(&id) {
+            Some(&(depth, slot)) => self.env_list.set_var_at(depth, slot, value),
+            None => self.env_list.set_global(&var.lexeme(), value),
+        }
+        .map_err(|_| RuntimeError::UndefinedVariable(var.to_owned()))
     }
 
+    fn evaluate_increment(
+        &mut self,
+        id: ExprId,
+        var: &Token,
+        operator: &Token,
+        prefix: bool,
+    ) -> Result<LoxValue, RuntimeError> {
+        let LoxValue::Number(old) = self.evaluate_var(id, var)? else {
+            return Err(RuntimeError::InvalidUnaryOperand(operator.clone()));
+        };
+        let new = match operator.token_type() {
+            TokenType::PlusPlus => old + 1.0,
+            _ => old - 1.0,
+        };
+        self.evaluate_assignment(id, var, LoxValue::Number(new))?;
+        Ok(LoxValue::Number(if prefix { new } else { old }))
+    }
+
+    /// Evaluates to one of the operands rather than a bool, e.g. `1 and 2` is `2`
+    /// and `nil or "x"` is `"x"`; the right operand is only evaluated if the left
+    /// one doesn't already decide the result, as in `false and f()`
     fn evaluate_logical(
         &mut self,
         left: &Expr,
@@ -216,10 +728,266 @@ impl Interpreter {
         }
     }
 
+    fn evaluate_call(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+    ) -> Result<LoxValue, RuntimeError> {
+        let callee = self.evaluate(callee)?;
+        let arguments = arguments
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.call_value(callee, paren, arguments)
+    }
+
+    /// Checks the number of arguments and calls a function, native function or class
+    fn call_value(
+        &mut self,
+        callee: LoxValue,
+        paren: &Token,
+        arguments: Vec<LoxValue>,
+    ) -> Result<LoxValue, RuntimeError> {
+        match callee {
+            LoxValue::Function(function) => {
+                if arguments.len() != function.arity() {
+                    return Err(RuntimeError::ArityMismatch(
+                        paren.clone(),
+                        function.arity(),
+                        arguments.len(),
+                    ));
+                }
+                self.call_function(&function, paren, arguments)
+            }
+            LoxValue::NativeFn(native) => {
+                if arguments.len() != native.arity {
+                    return Err(RuntimeError::ArityMismatch(
+                        paren.clone(),
+                        native.arity,
+                        arguments.len(),
+                    ));
+                }
+                (native.fun)(self, paren, &arguments)
+            }
+            LoxValue::Class(class) => {
+                let initializer = class.find_method("init");
+                let arity = initializer.as_ref().map_or(0, |init| init.arity());
+                if arguments.len() != arity {
+                    return Err(RuntimeError::ArityMismatch(
+                        paren.clone(),
+                        arity,
+                        arguments.len(),
+                    ));
+                }
+
+                let instance = Rc::new(RefCell::new(LoxInstance::new(class)));
+                if let Some(initializer) = initializer {
+                    let initializer = initializer.bind(Rc::clone(&instance));
+                    self.call_function(&initializer, paren, arguments)?;
+                }
+                Ok(LoxValue::Instance(instance))
+            }
+            _ => Err(RuntimeError::NotCallable(paren.clone())),
+        }
+    }
+
+    fn evaluate_get(&mut self, object: &Expr, name: &Token) -> Result<LoxValue, RuntimeError> {
+        let value = match self.evaluate(object)? {
+            LoxValue::Instance(instance) => LoxInstance::get(&instance, &name.lexeme()),
+            LoxValue::Class(class) => class
+                .find_static_method(&name.lexeme())
+                .map(LoxValue::Function),
+            _ => return Err(RuntimeError::OnlyInstancesHaveProperties(name.clone())),
+        };
+        let value = value.ok_or_else(|| RuntimeError::UndefinedProperty(name.clone()))?;
+        self.call_getter(value, name)
+    }
+
+    /// Runs a bound getter and returns its result, any other property is returned unchanged
+    fn call_getter(&mut self, property: LoxValue, name: &Token) -> Result<LoxValue, RuntimeError> {
+        match property {
+            LoxValue::Function(method) if method.declaration.is_getter => {
+                self.call_function(&method, name, Vec::new())
+            }
+            property => Ok(property),
+        }
+    }
+
+    fn evaluate_super(
+        &mut self,
+        id: ExprId,
+        keyword: &Token,
+        method: &Token,
+    ) -> Result<LoxValue, RuntimeError> {
+        let (depth, slot) = *self
+            .locals
+            .get(&id)
+            .ok_or_else(|| RuntimeError::UndefinedVariable(keyword.clone()))?;
+        let Ok(LoxValue::Class(superclass)) = self.env_list.get_var_at(depth, slot) else {
+            return Err(RuntimeError::UndefinedVariable(keyword.clone()));
+        };
+        // `this` is always bound alone in the scope just inside the one holding `super`
+        let Ok(LoxValue::Instance(instance)) = self.env_list.get_var_at(depth - 1, 0) else {
+            return Err(RuntimeError::UndefinedVariable(keyword.clone()));
+        };
+
+        let bound = superclass
+            .find_method(&method.lexeme())
+            .ok_or_else(|| RuntimeError::UndefinedProperty(method.clone()))?
+            .bind(instance);
+        self.call_getter(LoxValue::Function(Rc::new(bound)), method)
+    }
+
+    fn evaluate_set(
+        &mut self,
+        object: &Expr,
+        name: &Token,
+        value: &Expr,
+    ) -> Result<LoxValue, RuntimeError> {
+        let LoxValue::Instance(instance) = self.evaluate(object)? else {
+            return Err(RuntimeError::OnlyInstancesHaveFields(name.clone()));
+        };
+        let value = self.evaluate(value)?;
+        instance.borrow_mut().set(&name.lexeme(), value.clone());
+        Ok(value)
+    }
+
+    fn evaluate_index(
+        &mut self,
+        collection: &Expr,
+        bracket: &Token,
+        index: &Expr,
+    ) -> Result<LoxValue, RuntimeError> {
+        let collection = self.evaluate(collection)?;
+        let index = self.evaluate(index)?;
+        match collection {
+            LoxValue::List(list) => {
+                let list = list.borrow();
+                let i = Self::list_index(&list, &index, bracket)?;
+                Ok(list[i].clone())
+            }
+            // Missing keys read as nil, like unset variables in many scripting languages
+            LoxValue::Map(map) => {
+                let key = Self::map_key(index, bracket)?;
+                Ok(map.borrow().get(&key).cloned().unwrap_or(LoxValue::Nil))
+            }
+            _ => Err(RuntimeError::NotIndexable(bracket.clone())),
+        }
+    }
+
+    fn evaluate_index_set(
+        &mut self,
+        collection: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<LoxValue, RuntimeError> {
+        let collection = self.evaluate(collection)?;
+        let index = self.evaluate(index)?;
+        let value = self.evaluate(value)?;
+        match collection {
+            LoxValue::List(list) => {
+                let mut list = list.borrow_mut();
+                let i = Self::list_index(&list, &index, bracket)?;
+                list[i] = value.clone();
+            }
+            LoxValue::Map(map) => {
+                let key = Self::map_key(index, bracket)?;
+                map.borrow_mut().insert(key, value.clone());
+            }
+            _ => return Err(RuntimeError::NotIndexable(bracket.clone())),
+        }
+        Ok(value)
+    }
+
+    fn evaluate_map_literal(
+        &mut self,
+        brace: &Token,
+        entries: &[(Expr, Expr)],
+    ) -> Result<LoxValue, RuntimeError> {
+        let mut map = HashMap::new();
+        for (key, value) in entries {
+            let key = self.evaluate(key)?;
+            let key = Self::map_key(key, brace)?;
+            map.insert(key, self.evaluate(value)?);
+        }
+        Ok(LoxValue::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn map_key(key: LoxValue, token: &Token) -> Result<MapKey, RuntimeError> {
+        MapKey::new(key).ok_or_else(|| RuntimeError::InvalidMapKey(token.clone()))
+    }
+
+    /// Converts `index` to a position in `list`, it must be a whole number within bounds
+    fn list_index(
+        list: &[LoxValue],
+        index: &LoxValue,
+        bracket: &Token,
+    ) -> Result<usize, RuntimeError> {
+        let LoxValue::Number(n) = *index else {
+            return Err(RuntimeError::InvalidIndex(bracket.clone()));
+        };
+        if n < 0.0 || n.fract() != 0.0 {
+            return Err(RuntimeError::InvalidIndex(bracket.clone()));
+        }
+        if n >= list.len() as f64 {
+            return Err(RuntimeError::IndexOutOfBounds(bracket.clone()));
+        }
+        Ok(n as usize)
+    }
+
+    /// Executes the body of `function` in a new scope on top of its closure,
+    /// with each parameter bound to the corresponding argument
+    fn call_function(
+        &mut self,
+        function: &LoxFunction,
+        paren: &Token,
+        arguments: Vec<LoxValue>,
+    ) -> Result<LoxValue, RuntimeError> {
+        self.check_limits(paren)?;
+        if self.call_depth == self.max_call_depth {
+            return Err(RuntimeError::StackOverflow(paren.clone()));
+        }
+
+        let mut env_list = function.closure.clone();
+        env_list.push_new_env();
+        for (param, arg) in function.declaration.params.iter().zip(arguments) {
+            env_list.declare_var(&param.lexeme(), arg);
+        }
+
+        let previous = std::mem::replace(&mut self.env_list, env_list);
+        self.call_depth += 1;
+        let result = function
+            .declaration
+            .body
+            .iter()
+            .try_for_each(|stmt| self.execute(stmt));
+        self.call_depth -= 1;
+        self.env_list = previous;
+
+        match result {
+            Err(Unwind::Error(err)) => Err(err),
+            _ if function.is_initializer => Ok(function
+                .closure
+                .get_var_at(0, 0)
+                .expect("initializer should be bound to an instance")),
+            Ok(()) => Ok(LoxValue::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Break | Unwind::Continue) => {
+                unreachable!("resolver rejects loop control outside of loops")
+            }
+        }
+    }
+
+    /// Adds two numbers or concatenates two strings
+    /// Any other pairing, including mixing a string with a number (`"a" + 1`), is an error
     fn plus(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l + r)),
-            (LoxValue::String(l), LoxValue::String(r)) => Ok(LoxValue::String(l + &r)),
+            (LoxValue::String(l), LoxValue::String(r)) => {
+                Ok(LoxValue::String(format!("{l}{r}").into()))
+            }
             _ => Err(()),
         }
     }
@@ -229,151 +997,235 @@ impl Interpreter {
             _ => Err(()),
         }
     }
+    /// A string times a whole, non-negative number repeats the string
+    /// The operands can come in either order, so `"ab" * 2` and `2 * "ab"` are both `"abab"`
     fn multiply(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l * r)),
+            (LoxValue::String(s), LoxValue::Number(n))
+            | (LoxValue::Number(n), LoxValue::String(s)) => {
+                if n < 0.0 || n.fract() != 0.0 {
+                    return Err(());
+                }
+                Ok(LoxValue::String(s.repeat(n as usize).into()))
+            }
             _ => Err(()),
         }
     }
+    /// Whether `multiply` would repeat a string past `MAX_REPEATED_LEN`
+    fn repeats_too_long(left: &LoxValue, right: &LoxValue) -> bool {
+        match (left, right) {
+            (LoxValue::String(s), LoxValue::Number(n))
+            | (LoxValue::Number(n), LoxValue::String(s)) => {
+                // A count too big for usize saturates, so the check below still fails
+                *n >= 0.0
+                    && n.fract() == 0.0
+                    && (*n as usize)
+                        .checked_mul(s.len())
+                        .is_none_or(|len| len > MAX_REPEATED_LEN)
+            }
+            _ => false,
+        }
+    }
     fn divide(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l / r)),
             _ => Err(()),
         }
     }
+    /// Remainder with the sign of the dividend, e.g. `-7 % 3` is `-1`
+    fn modulo(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
+        match (left, right) {
+            (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l % r)),
+            _ => Err(()),
+        }
+    }
+    /// Only dividing (`/` or `%`) a number by the number zero is an error of its own,
+    /// other operand types are left to the operator's usual checks
+    fn divides_by_zero(left: &LoxValue, right: &LoxValue) -> bool {
+        matches!((left, right), (LoxValue::Number(_), LoxValue::Number(r)) if *r == 0.0)
+    }
     fn not_equal(left: LoxValue, right: LoxValue) -> LoxValue {
         LoxValue::Bool(left != right)
     }
     fn equal(left: LoxValue, right: LoxValue) -> LoxValue {
         LoxValue::Bool(left == right)
     }
+    /// Compares two numbers, or two strings lexicographically
+    /// Any other operands, including two bools or two nils, can't be ordered,
+    /// unlike `==` which works for every pair of values
     fn greater(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l > r)),
+            (LoxValue::String(l), LoxValue::String(r)) => Ok(LoxValue::Bool(l > r)),
             _ => Err(()),
         }
     }
     fn greater_equal(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l >= r)),
+            (LoxValue::String(l), LoxValue::String(r)) => Ok(LoxValue::Bool(l >= r)),
             _ => Err(()),
         }
     }
     fn less(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l < r)),
+            (LoxValue::String(l), LoxValue::String(r)) => Ok(LoxValue::Bool(l < r)),
             _ => Err(()),
         }
     }
     fn less_equal(left: LoxValue, right: LoxValue) -> Result<LoxValue, ()> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l <= r)),
+            (LoxValue::String(l), LoxValue::String(r)) => Ok(LoxValue::Bool(l <= r)),
             _ => Err(()),
         }
     }
-}
-
-impl EnvironmentList {
-    fn new() -> Self {
-        Self {
-            env_list: vec![Environment::new()],
-        }
-    }
-
-    fn declare_var(&mut self, name: &str, val: LoxValue) {
-        self.last_env_mut().declare_var(name, val);
+    /// Applies a bitwise operator to two integral numbers
+    /// `op` returns None when its result is undefined, like shifting by 64 or more bits
+    fn bitwise(
+        left: LoxValue,
+        right: LoxValue,
+        op: fn(i64, i64) -> Option<i64>,
+    ) -> Result<LoxValue, ()> {
+        let result = op(Self::integer(&left)?, Self::integer(&right)?).ok_or(())?;
+        Ok(LoxValue::Number(result as f64))
     }
-
-    fn get_var(&self, name: &str) -> Result<LoxValue, ()> {
-        // TODO: do the for loop with the FP way
-        for env in self.env_list.iter().rev() {
-            let value = env.get_var(name);
-            if value.is_ok() {
-                return value;
-            }
-        }
-        return Err(());
-    }
-
-    fn set_var(&mut self, name: &str, val: LoxValue) -> Result<LoxValue, ()> {
-        // TODO: do the for loop with the FP way
-        for env in self.env_list.iter_mut().rev() {
-            let value = env.set_var(name, val.clone());
-            if value.is_ok() {
-                return value;
+    /// Bitwise operators work on numbers converted to `i64`, which must be whole
+    /// and within its range, so `1.5 & 1` is an error rather than rounding
+    fn integer(val: &LoxValue) -> Result<i64, ()> {
+        match *val {
+            // `i64::MAX as f64` rounds up to 2^63, which is already out of range
+            LoxValue::Number(n)
+                if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 =>
+            {
+                Ok(n as i64)
             }
+            _ => Err(()),
         }
-        return Err(());
-        // self.last_env_mut().set_var(name, val)
-    }
-
-    fn push_new_env(&mut self) {
-        self.env_list.push(Environment::new());
-    }
-
-    fn pop_env(&mut self) {
-        self.env_list
-            .pop()
-            .expect("env_list should not be empty when popped");
     }
+}
 
-    fn last_env_mut(&mut self) -> &mut Environment {
-        self.env_list
-            .last_mut()
-            .expect("env_list should never be empty")
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Self::Error(err)
     }
 }
 
-impl Environment {
-    fn new() -> Self {
-        Environment {
-            map: HashMap::new(),
-        }
+impl RuntimeError {
+    /// An error for a native called with an argument it can't take
+    /// For `sqrt("a")`, `invalid_argument(paren, "sqrt", "a number", &arg)` gives
+    /// "'sqrt' expects a number, got string"
+    pub fn invalid_argument(paren: &Token, native: &str, expected: &str, got: &LoxValue) -> Self {
+        let message = format!("'{native}' expects {expected}, got {}", got.type_name());
+        Self::InvalidArgument(paren.clone(), message)
     }
 
-    fn declare_var(&mut self, name: &str, val: LoxValue) {
-        self.map.insert(name.to_string(), val);
+    pub fn to_err_msg(&self) -> String {
+        self.to_string()
     }
 
-    fn get_var(&self, name: &str) -> Result<LoxValue, ()> {
-        self.map.get(name).cloned().ok_or(())
+    pub fn message(&self) -> String {
+        let message = match self {
+            Self::InvalidUnaryOperand(_) => "Invalid unary operand",
+            Self::UndefinedVariable(_) => "Undefined variable",
+            Self::NotCallable(_) => "Can only call functions and classes",
+            Self::OnlyInstancesHaveProperties(_) => "Only instances and classes have properties",
+            Self::OnlyInstancesHaveFields(_) => "Only instances have fields",
+            Self::UndefinedProperty(_) => "Undefined property",
+            Self::SuperclassMustBeClass(_) => "Superclass must be a class",
+            Self::DivisionByZero(_) => "Division by zero",
+            Self::StringTooLong(_) => "Repeated string is too long",
+            Self::StackOverflow(_) => "Stack overflow",
+            Self::BudgetExceeded(_) => "Step budget exceeded",
+            Self::Cancelled(_) => "Cancelled",
+            Self::NotIndexable(_) => "Only lists and maps can be indexed",
+            Self::InvalidMapKey(_) => "Map keys must be strings or numbers",
+            Self::InvalidIndex(_) => "Index must be a non-negative integer",
+            Self::IndexOutOfBounds(_) => "Index out of bounds",
+            Self::SqrtOfNegative(_) => "Can't take the square root of a negative number",
+            Self::PopFromEmptyList(_) => "Can't pop from an empty list",
+            Self::InvalidNumber(_) => "Can't convert string to a number",
+            Self::ReadFailed(_) => "Failed to read input",
+            Self::WriteFailed(_) => "Failed to write output",
+            Self::AssertionFailed(_) => "Assertion failed",
+            Self::InvalidBinaryOperand(operator, left, right) => {
+                let expected = match operator.token_type() {
+                    TokenType::Plus
+                    | TokenType::Greater
+                    | TokenType::GreaterEqual
+                    | TokenType::Less
+                    | TokenType::LessEqual => "two numbers or two strings",
+                    TokenType::Star => "two numbers or a string and a non-negative whole number",
+                    TokenType::Ampersand | TokenType::Pipe | TokenType::Caret => {
+                        "whole numbers that fit in 64 bits"
+                    }
+                    TokenType::LessLess | TokenType::GreaterGreater => {
+                        "whole numbers that fit in 64 bits, with a shift of 0 to 63"
+                    }
+                    _ => "numbers",
+                };
+                return format!(
+                    "Operands must be {expected} for '{operator}', got {left} and {right}"
+                );
+            }
+            Self::InvalidArgument(_, message) => return message.clone(),
+            Self::ArityMismatch(_, expected, got) => {
+                return format!("Expected {expected} arguments but got {got}");
+            }
+            Self::AssertionNotEqual(_, left, right) => {
+                return format!("Assertion failed: {left} != {right}");
+            }
+        };
+        message.to_string()
     }
 
-    fn set_var(&mut self, name: &str, val: LoxValue) -> Result<LoxValue, ()> {
-        if self.map.contains_key(name) {
-            self.map.insert(name.to_string(), val.clone());
-            Ok(val)
-        } else {
-            Err(())
+    /// The token the error is reported at
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::InvalidBinaryOperand(t, ..)
+            | Self::InvalidUnaryOperand(t)
+            | Self::UndefinedVariable(t)
+            | Self::NotCallable(t)
+            | Self::ArityMismatch(t, ..)
+            | Self::OnlyInstancesHaveProperties(t)
+            | Self::OnlyInstancesHaveFields(t)
+            | Self::UndefinedProperty(t)
+            | Self::SuperclassMustBeClass(t)
+            | Self::DivisionByZero(t)
+            | Self::StringTooLong(t)
+            | Self::StackOverflow(t)
+            | Self::BudgetExceeded(t)
+            | Self::Cancelled(t)
+            | Self::NotIndexable(t)
+            | Self::InvalidMapKey(t)
+            | Self::InvalidIndex(t)
+            | Self::IndexOutOfBounds(t)
+            | Self::InvalidArgument(t, ..)
+            | Self::SqrtOfNegative(t)
+            | Self::PopFromEmptyList(t)
+            | Self::InvalidNumber(t)
+            | Self::ReadFailed(t)
+            | Self::WriteFailed(t)
+            | Self::AssertionFailed(t)
+            | Self::AssertionNotEqual(t, ..) => t,
         }
     }
 }
 
-impl LoxValue {
-    fn truthiness(&self) -> bool {
-        !matches!(self, Self::Nil) && !matches!(self, Self::Bool(false))
-    }
-}
-
-impl fmt::Display for LoxValue {
+/// Renders the error on one line, e.g. `[line 2:7] Division by zero`
+impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Nil => f.write_str("nil"),
-            Self::Bool(b) => write!(f, "{b}"),
-            Self::Number(n) => write!(f, "{n}"),
-            Self::String(s) => write!(f, "{s}"),
-        }
+        let token = self.token();
+        write!(
+            f,
+            "[line {}:{}] {}",
+            token.line(),
+            token.column(),
+            self.message()
+        )
     }
 }
 
-impl RuntimeError {
-    pub fn to_err_msg(&self) -> String {
-        let (warning, line) = match self {
-            Self::InvalidBinaryOperand(t) => ("Invalid binary operand", t.line()),
-            Self::InvalidUnaryOperand(t) => ("Invalid unary operand", t.line()),
-            Self::UnexpectedLiteralTokenType(t) => ("Unexpected literal token type", t.line()),
-            Self::UndefinedVariable(t) => ("Undefined variable", t.line()),
-        };
-        format!("{}\n[line {}]", warning, line)
-    }
-}
+impl std::error::Error for RuntimeError {}
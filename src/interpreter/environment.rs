@@ -0,0 +1,199 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::lox_value::LoxValue;
+
+/// Chain of scopes from the innermost local scope out to the outermost one,
+/// plus the global environment outside all of them
+/// Environments are shared so that functions can hold on to the chain they were declared in
+#[derive(Clone)]
+pub struct EnvironmentList {
+    globals: Rc<RefCell<HashMap<String, LoxValue>>>,
+    innermost: Option<Rc<RefCell<Environment>>>,
+}
+
+/// A local scope, whose variables are found by the slot the resolver gave them
+/// Slots are handed out in declaration order, so declaring a variable appends it
+struct Environment {
+    slots: Vec<LoxValue>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl EnvironmentList {
+    pub fn new() -> Self {
+        Self {
+            globals: Rc::new(RefCell::new(HashMap::new())),
+            innermost: None,
+        }
+    }
+
+    /// Declares the variable in the innermost scope, or as a global outside all local scopes
+    pub fn declare_var(&mut self, name: &str, val: LoxValue) {
+        match &self.innermost {
+            Some(env) => env.borrow_mut().slots.push(val),
+            None => {
+                self.globals.borrow_mut().insert(name.to_string(), val);
+            }
+        }
+    }
+
+    /// Gets the variable in `slot` of the scope `depth` scopes out from the innermost one
+    pub fn get_var_at(&self, depth: usize, slot: usize) -> Result<LoxValue, ()> {
+        self.ancestor(depth)
+            .borrow()
+            .slots
+            .get(slot)
+            .cloned()
+            .ok_or(())
+    }
+
+    /// Sets the variable in `slot` of the scope `depth` scopes out from the innermost one
+    pub fn set_var_at(&mut self, depth: usize, slot: usize, val: LoxValue) -> Result<LoxValue, ()> {
+        let env = self.ancestor(depth);
+        let mut env = env.borrow_mut();
+        let var = env.slots.get_mut(slot).ok_or(())?;
+        *var = val.clone();
+        Ok(val)
+    }
+
+    pub fn get_global(&self, name: &str) -> Result<LoxValue, ()> {
+        self.globals.borrow().get(name).cloned().ok_or(())
+    }
+
+    /// Assigns in place, so the name is neither hashed twice nor copied into a new key
+    pub fn set_global(&mut self, name: &str, val: LoxValue) -> Result<LoxValue, ()> {
+        let mut globals = self.globals.borrow_mut();
+        let var = globals.get_mut(name).ok_or(())?;
+        *var = val.clone();
+        Ok(val)
+    }
+
+    /// Makes a new scope enclosed by the current innermost one the innermost scope
+    pub fn push_new_env(&mut self) {
+        let env = Environment {
+            slots: Vec::new(),
+            enclosing: self.innermost.take(),
+        };
+        self.innermost = Some(Rc::new(RefCell::new(env)));
+    }
+
+    /// Makes the scope enclosing the innermost one the innermost scope again
+    /// The popped scope keeps its link, as closures declared in it may still use it
+    pub fn pop_env(&mut self) {
+        let env = self
+            .innermost
+            .take()
+            .expect("there should be a local scope when one is popped");
+        self.innermost = env.borrow().enclosing.clone();
+    }
+
+    /// Panics if `depth` is not smaller than the number of local scopes
+    fn ancestor(&self, depth: usize) -> Rc<RefCell<Environment>> {
+        let mut env = self
+            .innermost
+            .clone()
+            .expect("resolved depth should be within the scope chain");
+        for _ in 0..depth {
+            let enclosing = env
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("resolved depth should be within the scope chain");
+            env = enclosing;
+        }
+        env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ten nested scopes, each with a string variable in slot 0 naming how deep it is
+    fn nested_scopes() -> EnvironmentList {
+        let mut envs = EnvironmentList::new();
+        for depth in (0..=10).rev() {
+            envs.push_new_env();
+            envs.declare_var("s", LoxValue::String(depth.to_string().into()));
+        }
+        envs
+    }
+
+    #[test]
+    fn assigns_into_a_variable_ten_scopes_out() {
+        let mut envs = nested_scopes();
+        let val: Rc<str> = "assigned".into();
+        let returned = envs.set_var_at(10, 0, LoxValue::String(Rc::clone(&val)));
+        assert!(matches!(returned, Ok(LoxValue::String(ref s)) if Rc::ptr_eq(s, &val)));
+        // Held here, in the scope assigned to, and by the returned value, with no stray copies
+        assert_eq!(Rc::strong_count(&val), 3);
+        for depth in 0..10 {
+            let LoxValue::String(s) = envs.get_var_at(depth, 0).unwrap() else {
+                panic!("slot should hold a string");
+            };
+            assert_eq!(*s, *depth.to_string());
+        }
+    }
+
+    #[test]
+    fn reading_a_string_shares_it_instead_of_copying() {
+        let mut envs = EnvironmentList::new();
+        let val: Rc<str> = "x".repeat(1 << 20).into();
+        envs.declare_var("global", LoxValue::String(Rc::clone(&val)));
+        envs.push_new_env();
+        envs.declare_var("local", LoxValue::String(Rc::clone(&val)));
+        for _ in 0..1000 {
+            let (LoxValue::String(global), LoxValue::String(local)) = (
+                envs.get_global("global").unwrap(),
+                envs.get_var_at(0, 0).unwrap(),
+            ) else {
+                panic!("variables should hold strings");
+            };
+            assert!(Rc::ptr_eq(&global, &val) && Rc::ptr_eq(&local, &val));
+        }
+        assert_eq!(Rc::strong_count(&val), 3);
+    }
+
+    #[test]
+    fn looks_up_variables_through_enclosing_scopes() {
+        let mut envs = EnvironmentList::new();
+        envs.declare_var("g", LoxValue::Number(0.0));
+        envs.push_new_env();
+        envs.declare_var("a", LoxValue::Number(1.0));
+        envs.push_new_env();
+        envs.declare_var("b", LoxValue::Number(2.0));
+        assert!(matches!(envs.get_var_at(0, 0), Ok(LoxValue::Number(n)) if n == 2.0));
+        assert!(matches!(envs.get_var_at(1, 0), Ok(LoxValue::Number(n)) if n == 1.0));
+        assert!(matches!(envs.get_global("g"), Ok(LoxValue::Number(n)) if n == 0.0));
+        assert!(envs.get_var_at(0, 1).is_err());
+        assert!(envs.get_global("a").is_err());
+    }
+
+    #[test]
+    fn assignment_reaches_the_enclosing_scope_and_outlives_the_inner_one() {
+        let mut envs = EnvironmentList::new();
+        envs.push_new_env();
+        envs.declare_var("a", LoxValue::Nil);
+        envs.push_new_env();
+        envs.set_var_at(1, 0, LoxValue::Bool(true)).unwrap();
+        envs.pop_env();
+        assert!(matches!(envs.get_var_at(0, 0), Ok(LoxValue::Bool(true))));
+    }
+
+    #[test]
+    fn a_captured_chain_keeps_its_scope_after_it_is_popped() {
+        let mut envs = EnvironmentList::new();
+        envs.push_new_env();
+        envs.declare_var("x", LoxValue::Number(1.0));
+        // What a closure declared here holds on to
+        let mut captured = envs.clone();
+        envs.pop_env();
+        envs.push_new_env();
+        envs.declare_var("y", LoxValue::Number(2.0));
+
+        captured.set_var_at(0, 0, LoxValue::Number(3.0)).unwrap();
+        assert!(matches!(captured.get_var_at(0, 0), Ok(LoxValue::Number(n)) if n == 3.0));
+        assert!(matches!(envs.get_var_at(0, 0), Ok(LoxValue::Number(n)) if n == 2.0));
+    }
+}
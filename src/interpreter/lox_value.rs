@@ -0,0 +1,316 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::environment::EnvironmentList;
+use super::{Interpreter, RuntimeError};
+use crate::ast::FunctionDecl;
+use crate::scanner::token::Token;
+
+/// A value produced by evaluating Lox code
+/// Strings, functions, classes, instances, lists and maps are shared by reference, so cloning is cheap
+/// Strings are immutable and compare by content, the other shared values by identity
+#[derive(Clone)]
+pub enum LoxValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    /// A function or method declared in Lox
+    Function(Rc<LoxFunction>),
+    /// A function implemented in Rust, such as `clock`
+    NativeFn(Rc<NativeFn>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    /// A mutable list, shared so that every variable holding it sees changes to it
+    List(Rc<RefCell<Vec<LoxValue>>>),
+    /// A mutable map, shared like lists
+    Map(Rc<RefCell<HashMap<MapKey, LoxValue>>>),
+}
+
+/// The values that can key a map
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    String(Rc<str>),
+    // The bits of the number, with `-0` stored as `0` so the two find the same entry
+    Number(u64),
+}
+
+pub struct LoxFunction {
+    pub declaration: Rc<FunctionDecl>,
+    pub closure: EnvironmentList,
+    // Initializers always return `this`
+    pub is_initializer: bool,
+}
+
+/// Rust implementation of a native function, given the interpreter running the call,
+/// the closing paren of the call to report errors at and the already evaluated arguments
+pub type NativeFnBody =
+    Box<dyn Fn(&mut Interpreter, &Token, &[LoxValue]) -> Result<LoxValue, RuntimeError>>;
+
+pub struct NativeFn {
+    pub name: String,
+    pub arity: usize,
+    pub fun: NativeFnBody,
+}
+
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    pub superclass: Option<Rc<LoxClass>>,
+    pub methods: HashMap<String, Rc<LoxFunction>>,
+    // Called on the class itself, so they are never bound to an instance
+    pub static_methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+#[derive(Debug)]
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    pub fields: HashMap<String, LoxValue>,
+}
+
+impl LoxValue {
+    pub fn truthiness(&self) -> bool {
+        !matches!(self, Self::Nil) && !matches!(self, Self::Bool(false))
+    }
+
+    /// Name of the kind of value, as returned by the `type` native
+    /// The names are stable, so hosts and scripts can match on them
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::Bool(_) => "bool",
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::Function(_) | Self::NativeFn(_) => "function",
+            Self::Class(_) => "class",
+            Self::Instance(_) => "instance",
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+        }
+    }
+}
+
+/// Lox's `==`, which never fails
+/// Values of different types are never equal, so `nil` only equals `nil` and `1 != "1"`
+/// Numbers, strings and bools compare by value, everything else by identity
+impl PartialEq for LoxValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(l), Self::Bool(r)) => l == r,
+            (Self::Number(l), Self::Number(r)) => l == r,
+            (Self::String(l), Self::String(r)) => l == r,
+            (Self::Function(l), Self::Function(r)) => Rc::ptr_eq(l, r),
+            (Self::NativeFn(l), Self::NativeFn(r)) => Rc::ptr_eq(l, r),
+            (Self::Class(l), Self::Class(r)) => Rc::ptr_eq(l, r),
+            (Self::Instance(l), Self::Instance(r)) => Rc::ptr_eq(l, r),
+            (Self::List(l), Self::List(r)) => Rc::ptr_eq(l, r),
+            (Self::Map(l), Self::Map(r)) => Rc::ptr_eq(l, r),
+            // Listed out rather than `_`, so a new variant can't be left out by accident
+            (
+                Self::Nil
+                | Self::Bool(_)
+                | Self::Number(_)
+                | Self::String(_)
+                | Self::Function(_)
+                | Self::NativeFn(_)
+                | Self::Class(_)
+                | Self::Instance(_)
+                | Self::List(_)
+                | Self::Map(_),
+                _,
+            ) => false,
+        }
+    }
+}
+
+impl fmt::Display for LoxValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nil => f.write_str("nil"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Function(function) => write!(f, "{function}"),
+            Self::NativeFn(_) => f.write_str("<native fn>"),
+            Self::Class(class) => write!(f, "{}", class.name),
+            Self::Instance(instance) => write!(f, "{} instance", instance.borrow().class.name),
+            Self::List(list) => fmt_once(Rc::as_ptr(list).cast(), f, "[...]", |f| {
+                f.write_str("[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                f.write_str("]")
+            }),
+            // Entries are shown in no particular order
+            Self::Map(map) => fmt_once(Rc::as_ptr(map).cast(), f, "{...}", |f| {
+                f.write_str("{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {value}", key.to_value())?;
+                }
+                f.write_str("}")
+            }),
+        }
+    }
+}
+
+// Written out instead of derived so that a list, map or instance containing itself
+// is shown once rather than recursing forever
+impl fmt::Debug for LoxValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nil => f.write_str("Nil"),
+            Self::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            Self::Number(n) => f.debug_tuple("Number").field(n).finish(),
+            Self::String(s) => f.debug_tuple("String").field(s).finish(),
+            Self::Function(function) => f.debug_tuple("Function").field(function).finish(),
+            Self::NativeFn(native) => f.debug_tuple("NativeFn").field(native).finish(),
+            Self::Class(class) => f.debug_tuple("Class").field(class).finish(),
+            Self::Instance(instance) => {
+                fmt_once(Rc::as_ptr(instance).cast(), f, "Instance(..)", |f| {
+                    f.debug_tuple("Instance").field(&instance.borrow()).finish()
+                })
+            }
+            Self::List(list) => fmt_once(Rc::as_ptr(list).cast(), f, "List(..)", |f| {
+                f.debug_tuple("List").field(&list.borrow()).finish()
+            }),
+            Self::Map(map) => fmt_once(Rc::as_ptr(map).cast(), f, "Map(..)", |f| {
+                f.debug_tuple("Map").field(&map.borrow()).finish()
+            }),
+        }
+    }
+}
+
+thread_local! {
+    // Lists, maps and instances that are part way through being formatted
+    static FORMATTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Formats the value at `ptr` with `fmt`, unless it is already being formatted further out,
+/// in which case it contains itself and `cycle` is written instead
+fn fmt_once(
+    ptr: *const (),
+    f: &mut fmt::Formatter<'_>,
+    cycle: &str,
+    fmt: impl FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+) -> fmt::Result {
+    if FORMATTING.with_borrow(|formatting| formatting.contains(&ptr)) {
+        return f.write_str(cycle);
+    }
+    FORMATTING.with_borrow_mut(|formatting| formatting.push(ptr));
+    let result = fmt(f);
+    FORMATTING.with_borrow_mut(|formatting| formatting.pop());
+    result
+}
+
+impl MapKey {
+    /// Returns None for values that can't be keys, which is anything but a string or number
+    pub fn new(value: LoxValue) -> Option<Self> {
+        match value {
+            LoxValue::String(s) => Some(Self::String(s)),
+            // Adding zero turns `-0` into `0`
+            LoxValue::Number(n) => Some(Self::Number((n + 0.0).to_bits())),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> LoxValue {
+        match self {
+            Self::String(s) => LoxValue::String(Rc::clone(s)),
+            Self::Number(bits) => LoxValue::Number(f64::from_bits(*bits)),
+        }
+    }
+}
+
+impl LoxFunction {
+    pub fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    /// Returns a copy of this method whose closure has `this` bound to `instance`
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut closure = self.closure.clone();
+        closure.push_new_env();
+        closure.declare_var("this", LoxValue::Instance(instance));
+        LoxFunction {
+            declaration: Rc::clone(&self.declaration),
+            closure,
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+impl LoxClass {
+    /// Looks up the method on this class, then up the superclass chain
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.methods.get(name).cloned().or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+
+    /// Looks up the static method on this class, then up the superclass chain
+    pub fn find_static_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.static_methods.get(name).cloned().or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_static_method(name))
+        })
+    }
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Fields shadow methods of the same name
+    /// Methods are returned bound to `instance`
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &str) -> Option<LoxValue> {
+        if let Some(field) = instance.borrow().fields.get(name) {
+            return Some(field.clone());
+        }
+        let method = instance.borrow().class.find_method(name)?;
+        let bound = method.bind(Rc::clone(instance));
+        Some(LoxValue::Function(Rc::new(bound)))
+    }
+
+    pub fn set(&mut self, name: &str, value: LoxValue) {
+        self.fields.insert(name.to_string(), value);
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.declaration.is_lambda() {
+            f.write_str("<fn>")
+        } else {
+            write!(f, "<fn {}>", self.declaration.name.lexeme())
+        }
+    }
+}
+
+// The closure can refer back to the function itself, so only the name is shown
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl fmt::Debug for NativeFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::lox_value::LoxValue;
+use super::{Interpreter, RuntimeError};
+use crate::scanner::token::Token;
+
+/// Seconds since the Unix epoch
+pub fn clock(_paren: &Token, _arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time should be after the Unix epoch");
+    Ok(LoxValue::Number(now.as_secs_f64()))
+}
+
+/// Name of the argument's kind of value, e.g. `"number"`
+pub fn type_of(_paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    Ok(LoxValue::String(arguments[0].type_name().into()))
+}
+
+/// Parses a string such as `"3.14"` into a number, surrounding whitespace is ignored
+/// Numbers are returned unchanged
+pub fn number(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    match &arguments[0] {
+        LoxValue::Number(n) => Ok(LoxValue::Number(*n)),
+        LoxValue::String(s) => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            // Rust also parses words like "inf" and "NaN", which aren't Lox numbers
+            .filter(|n| n.is_finite())
+            .map(LoxValue::Number)
+            .ok_or_else(|| RuntimeError::InvalidNumber(paren.clone())),
+        other => Err(RuntimeError::invalid_argument(
+            paren,
+            "number",
+            "a number or a string",
+            other,
+        )),
+    }
+}
+
+/// The argument as it would be printed, strings are returned unchanged
+pub fn str(
+    interpreter: &mut Interpreter,
+    _paren: &Token,
+    arguments: &[LoxValue],
+) -> Result<LoxValue, RuntimeError> {
+    match interpreter.call_to_string(arguments[0].clone())? {
+        LoxValue::String(s) => Ok(LoxValue::String(s)),
+        value => Ok(LoxValue::String(value.to_string().into())),
+    }
+}
+
+/// Reads a line from `input` without its line ending, or nil at the end of input
+/// Reads a byte at a time, so no input past the line is consumed, e.g. from the REPL's stdin
+pub fn read_line(paren: &Token, input: &mut dyn Read) -> Result<LoxValue, RuntimeError> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) if line.is_empty() => return Ok(LoxValue::Nil),
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) => line.push(byte[0]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(_) => return Err(RuntimeError::ReadFailed(paren.clone())),
+        }
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(LoxValue::String(String::from_utf8_lossy(&line).into()))
+}
+
+/// Writes the argument like `print` does, but without a newline
+pub fn write(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[LoxValue],
+) -> Result<LoxValue, RuntimeError> {
+    let value = interpreter.call_to_string(arguments[0].clone())?;
+    let mut out = interpreter.out.borrow_mut();
+    // Flushed so the text shows up before, say, waiting on `read_line`
+    write!(out, "{value}")
+        .and_then(|()| out.flush())
+        .map_err(|_| RuntimeError::WriteFailed(paren.clone()))?;
+    Ok(LoxValue::Nil)
+}
+
+/// Most decimal places `fmt` accepts, far more than an f64 has significant digits
+const MAX_DECIMALS: f64 = 100.0;
+
+/// Formats a number rounded to a whole number of decimal places, e.g. `fmt(1, 3)` is `"1.000"`
+pub fn fmt(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let [n, decimals] = arguments else {
+        unreachable!("arity is checked before natives are called");
+    };
+    let n = number_arg(paren, "fmt", n)?;
+    match decimals {
+        LoxValue::Number(d) if d.fract() == 0.0 && (0.0..=MAX_DECIMALS).contains(d) => {
+            let formatted = format!("{n:.*}", *d as usize);
+            Ok(LoxValue::String(formatted.into()))
+        }
+        // The number itself is shown, since its type alone doesn't say what is wrong with it
+        LoxValue::Number(d) => Err(RuntimeError::InvalidArgument(
+            paren.clone(),
+            format!(
+                "'fmt' expects a whole number of decimal places from 0 to {MAX_DECIMALS}, got {d}"
+            ),
+        )),
+        _ => Err(RuntimeError::invalid_argument(
+            paren,
+            "fmt",
+            "a whole number of decimal places",
+            decimals,
+        )),
+    }
+}
+
+/// Raises a runtime error if the argument is falsey
+pub fn assert(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    if arguments[0].truthiness() {
+        Ok(LoxValue::Nil)
+    } else {
+        Err(RuntimeError::AssertionFailed(paren.clone()))
+    }
+}
+
+/// Raises a runtime error showing both arguments unless they are equal by `==`
+pub fn assert_eq(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let [left, right] = arguments else {
+        unreachable!("arity is checked before natives are called");
+    };
+    if left == right {
+        Ok(LoxValue::Nil)
+    } else {
+        Err(RuntimeError::AssertionNotEqual(
+            paren.clone(),
+            left.clone(),
+            right.clone(),
+        ))
+    }
+}
+
+/// Number of elements in a list, entries in a map, or characters in a string
+pub fn len(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let len = match &arguments[0] {
+        LoxValue::List(list) => list.borrow().len(),
+        LoxValue::Map(map) => map.borrow().len(),
+        LoxValue::String(s) => s.chars().count(),
+        other => {
+            return Err(RuntimeError::invalid_argument(
+                paren,
+                "len",
+                "a list, map or string",
+                other,
+            ))
+        }
+    };
+    Ok(LoxValue::Number(len as f64))
+}
+
+/// Appends the second argument to the list given first
+pub fn push(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let LoxValue::List(list) = &arguments[0] else {
+        return Err(RuntimeError::invalid_argument(
+            paren,
+            "push",
+            "a list",
+            &arguments[0],
+        ));
+    };
+    list.borrow_mut().push(arguments[1].clone());
+    Ok(LoxValue::Nil)
+}
+
+/// Removes and returns the last element of a list
+pub fn pop(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let LoxValue::List(list) = &arguments[0] else {
+        return Err(RuntimeError::invalid_argument(
+            paren,
+            "pop",
+            "a list",
+            &arguments[0],
+        ));
+    };
+    let popped = list.borrow_mut().pop();
+    popped.ok_or_else(|| RuntimeError::PopFromEmptyList(paren.clone()))
+}
+
+/// The argument's value if it is a number, `native` is named in the error otherwise
+fn number_arg(paren: &Token, native: &str, argument: &LoxValue) -> Result<f64, RuntimeError> {
+    match argument {
+        LoxValue::Number(n) => Ok(*n),
+        _ => Err(RuntimeError::invalid_argument(
+            paren, native, "a number", argument,
+        )),
+    }
+}
+
+/// Absolute value of a number
+pub fn abs(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    Ok(LoxValue::Number(
+        number_arg(paren, "abs", &arguments[0])?.abs(),
+    ))
+}
+
+/// Largest whole number less than or equal to a number
+pub fn floor(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    Ok(LoxValue::Number(
+        number_arg(paren, "floor", &arguments[0])?.floor(),
+    ))
+}
+
+/// Smallest whole number greater than or equal to a number
+pub fn ceil(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    Ok(LoxValue::Number(
+        number_arg(paren, "ceil", &arguments[0])?.ceil(),
+    ))
+}
+
+/// Square root of a non-negative number, negative numbers are an error rather than NaN
+pub fn sqrt(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let n = number_arg(paren, "sqrt", &arguments[0])?;
+    if n < 0.0 {
+        return Err(RuntimeError::SqrtOfNegative(paren.clone()));
+    }
+    Ok(LoxValue::Number(n.sqrt()))
+}
+
+/// Smaller of two numbers
+pub fn min(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let a = number_arg(paren, "min", &arguments[0])?;
+    let b = number_arg(paren, "min", &arguments[1])?;
+    Ok(LoxValue::Number(a.min(b)))
+}
+
+/// Larger of two numbers
+pub fn max(paren: &Token, arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let a = number_arg(paren, "max", &arguments[0])?;
+    let b = number_arg(paren, "max", &arguments[1])?;
+    Ok(LoxValue::Number(a.max(b)))
+}
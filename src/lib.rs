@@ -0,0 +1,554 @@
+//! A tree-walking interpreter for Lox
+//!
+//! Besides the `rlox` binary, the interpreter can be embedded in other programs:
+//!
+//! ```
+//! let mut interpreter = rlox::Interpreter::new();
+//! rlox::run_source(&mut interpreter, "var greeting = \"hello\";").unwrap();
+//! rlox::run_source(&mut interpreter, "print greeting;").unwrap();
+//! ```
+
+use std::{
+    error, fmt, fs,
+    io::{self, Write},
+    process,
+};
+
+use ast::Stmt;
+use diagnostic::{Diagnostic, Span};
+use resolver::{ResolverError, ResolverWarning};
+use scanner::{token::Token, token_type::TokenType, ScanError, Scanner};
+
+use crate::{
+    parser::{Parser, ParserError},
+    resolver::Resolver,
+};
+
+pub use interpreter::{Interpreter, LoxValue, RuntimeError};
+
+pub mod ast;
+pub mod diagnostic;
+pub mod interpreter;
+pub mod parser;
+pub mod resolver;
+pub mod scanner;
+
+/// Runs `source` with `interpreter`, so state such as global variables
+/// is kept between calls
+/// Nothing is printed on stderr, errors are returned for the host to present
+///
+/// ```
+/// use rlox::{diagnostic::Severity, Interpreter, LoxError};
+///
+/// let mut interpreter = Interpreter::new();
+/// let Err(LoxError::Compile(diagnostics)) = rlox::run_source(&mut interpreter, "print (1;") else {
+///     panic!("a syntax error should stop the source from running");
+/// };
+/// assert_eq!(diagnostics[0].severity, Severity::Error);
+/// assert_eq!(diagnostics[0].message, "Expect ')'");
+/// ```
+pub fn run_source(interpreter: &mut Interpreter, source: &str) -> Result<(), LoxError> {
+    let mut lox = Lox::silent();
+    let Some(program) = lox.parse(interpreter, source.to_string(), Parser::parse) else {
+        return Err(LoxError::Compile(lox.diagnostics));
+    };
+    interpreter.interpret(program).map_err(LoxError::Runtime)
+}
+
+/// Why `run_source` failed
+#[derive(Debug)]
+pub enum LoxError {
+    /// The source didn't scan, parse or resolve, so none of it ran
+    /// Holds every error found, along with any warnings
+    Compile(Vec<Diagnostic>),
+    /// The source stopped running at this error, which converts into a `Diagnostic` as well
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compile(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("\n")?;
+                    }
+                    let Span { line, column, .. } = diagnostic.span;
+                    write!(f, "[line {line}:{column}] {}", diagnostic.message)?;
+                }
+                Ok(())
+            }
+            Self::Runtime(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl error::Error for LoxError {}
+
+/// Whether `source` stops in the middle of a construct, such as an unclosed block,
+/// so that the REPL should read more lines before running it
+fn is_incomplete(source: &str) -> bool {
+    match Scanner::new(source.to_string()).scan_tokens() {
+        Ok(tokens) => Parser::new(tokens)
+            .parse_repl()
+            .is_err_and(|errors| matches!(errors[0].token().token_type(), TokenType::Eof)),
+        Err(errors) => errors[0].unterminated,
+    }
+}
+
+/// Reads the script named on the command line, where `-` stands for stdin
+/// Exits with 66 (EX_NOINPUT) if it can't be read
+fn read_source(filename: &str) -> String {
+    let source = if filename == "-" {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(filename)
+    };
+    source.unwrap_or_else(|err| {
+        let reason = match err.kind() {
+            io::ErrorKind::NotFound => "No such file or directory".to_string(),
+            io::ErrorKind::PermissionDenied => "Permission denied".to_string(),
+            _ => err.to_string(),
+        };
+        eprintln!("Error: could not open '{filename}': {reason}");
+        process::exit(66);
+    })
+}
+
+pub struct Lox {
+    // TODO: can implement an error handler?
+    had_error: bool,
+    had_runtime_error: bool,
+    // Every error reported so far, for hosts that present errors themselves
+    diagnostics: Vec<Diagnostic>,
+    // Line the next source starts on, so that the REPL numbers lines across the whole session
+    first_line: usize,
+    // Whether errors and warnings are printed on stderr as well as kept as diagnostics
+    print_errors: bool,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Lox {
+            had_error: false,
+            had_runtime_error: false,
+            diagnostics: Vec::new(),
+            first_line: 1,
+            print_errors: true,
+        }
+    }
+
+    /// Keeps errors and warnings as diagnostics only, for hosts that present them themselves
+    fn silent() -> Self {
+        Self {
+            print_errors: false,
+            ..Self::new()
+        }
+    }
+
+    pub fn main(&mut self, args: Vec<String>) -> std::io::Result<()> {
+        self.main_with(&mut Interpreter::new(), args)
+    }
+
+    /// Like `main`, but runs everything in `interpreter`, e.g. one allowed deeper recursion
+    pub fn main_with(
+        &mut self,
+        interpreter: &mut Interpreter,
+        args: Vec<String>,
+    ) -> std::io::Result<()> {
+        match args.get(1..).unwrap_or_default() {
+            [] => self.run_prompt(interpreter)?,
+            [flag, filename] if flag == "--dump-tokens" => self.dump_tokens(filename),
+            [flag, filename] if flag == "--dump-ast" => self.dump_ast(interpreter, filename),
+            [flag, filename] if flag == "--dump-json" => self.dump_json(interpreter, filename),
+            [flag, code] if flag == "--eval" => self.run_script(interpreter, code.clone()),
+            [flag, ..] if flag == "--eval" => {
+                println!("--eval takes the code to run, and can't be combined with a script");
+                process::exit(64);
+            }
+            [filename] if !filename.starts_with("--") => self.run_file(interpreter, filename),
+            _ => {
+                println!("Usage: rlox [--dump-tokens | --dump-ast | --dump-json] [script]");
+                println!("       rlox --eval code");
+                process::exit(64);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_file(&mut self, interpreter: &mut Interpreter, filename: &str) {
+        let source = read_source(filename);
+        self.run_script(interpreter, source);
+    }
+
+    /// Runs a whole program, exiting with 65 on syntax errors and 70 on runtime errors
+    fn run_script(&mut self, interpreter: &mut Interpreter, source: String) {
+        self.run(interpreter, source);
+
+        if self.had_error {
+            process::exit(65);
+        }
+        if self.had_runtime_error {
+            process::exit(70);
+        }
+    }
+
+    /// Prints the tokens scanned from the file, one per line, without running it
+    fn dump_tokens(&mut self, filename: &str) {
+        let source = read_source(filename);
+
+        match Scanner::new(source).scan_tokens() {
+            Ok(tokens) => {
+                for token in tokens {
+                    println!("{} {} {}", token.token_type(), token.lexeme(), token.line());
+                }
+            }
+            Err(errors) => {
+                errors.into_iter().for_each(|e| self.scan_error(e));
+                process::exit(65);
+            }
+        }
+    }
+
+    /// Prints the syntax tree parsed from the file without running it
+    fn dump_ast(&mut self, interpreter: &mut Interpreter, filename: &str) {
+        let source = read_source(filename);
+        let Some(program) = self.parse(interpreter, source, Parser::parse) else {
+            process::exit(65);
+        };
+        for stmt in program {
+            println!("{stmt}");
+        }
+    }
+
+    fn dump_json(&mut self, interpreter: &mut Interpreter, filename: &str) {
+        let source = read_source(filename);
+        let Some(program) = self.parse(interpreter, source, Parser::parse) else {
+            process::exit(65);
+        };
+        println!("{}", ast::program_to_json(&program));
+    }
+
+    fn run_prompt(&mut self, interpreter: &mut Interpreter) -> std::io::Result<()> {
+        let mut stdout = io::stdout();
+        let stdin = io::stdin();
+        // Lines read so far for input that spans multiple lines
+        let mut source = String::new();
+
+        loop {
+            let prompt = if source.is_empty() { "> " } else { "... " };
+            stdout.write_all(prompt.as_bytes())?;
+            stdout.flush()?;
+
+            let mut line = String::new();
+
+            let n = match stdin.read_line(&mut line) {
+                Ok(bytes_read) => bytes_read,
+                Err(error) => {
+                    println!("{error}");
+                    return Err(error);
+                }
+            };
+
+            if n == 0 {
+                return Ok(());
+            }
+
+            if source.is_empty() && line.trim_start().starts_with(':') {
+                self.meta_command(interpreter, line.trim());
+                self.first_line += 1;
+                continue;
+            }
+
+            // A blank line gives up on the continuation so its errors get reported
+            let give_up = !source.is_empty() && line.trim().is_empty();
+            source.push_str(&line);
+            if !give_up && is_incomplete(&source) {
+                continue;
+            }
+
+            let source = std::mem::take(&mut source);
+            let lines = source.lines().count();
+            self.run_repl(interpreter, source);
+            self.first_line += lines;
+        }
+    }
+
+    /// Handles a REPL line starting with ':', which controls the REPL instead of running code
+    fn meta_command(&mut self, interpreter: &mut Interpreter, command: &str) {
+        match command {
+            ":reset" => interpreter.reset(),
+            _ => eprintln!("Unknown command '{command}', the only command is :reset"),
+        }
+    }
+
+    /// Runs `source` with `interpreter`, reporting errors on stderr
+    /// The errors are also kept as `diagnostics`
+    pub fn run(&mut self, interpreter: &mut Interpreter, source: String) {
+        let Some(program) = self.parse(interpreter, source, Parser::parse) else {
+            return;
+        };
+        if let Err(err) = interpreter.interpret(program) {
+            self.runtime_error(err);
+        }
+    }
+
+    /// Same as `run`, but echoes the value of a trailing expression statement
+    /// Errors from earlier entries are forgotten, so each entry starts afresh
+    fn run_repl(&mut self, interpreter: &mut Interpreter, source: String) {
+        self.had_error = false;
+        self.had_runtime_error = false;
+        self.diagnostics.clear();
+        let Some(program) = self.parse(interpreter, source, Parser::parse_repl) else {
+            return;
+        };
+        if let Err(err) = interpreter.interpret(program) {
+            self.runtime_error(err);
+        }
+    }
+
+    /// Scans, parses with `parse` and resolves `source` for `interpreter`
+    /// Returns None if the program should not be interpreted
+    fn parse(
+        &mut self,
+        interpreter: &mut Interpreter,
+        source: String,
+        parse: fn(&mut Parser) -> Result<Vec<Stmt>, Vec<ParserError>>,
+    ) -> Option<Vec<Stmt>> {
+        let tokens = match Scanner::with_first_line(source, self.first_line).scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                errors.into_iter().for_each(|e| self.scan_error(e));
+                return None;
+            }
+        };
+
+        let mut parser = Parser::new(tokens);
+        let program = match parse(&mut parser) {
+            Ok(program) => program,
+            Err(errors) => {
+                errors.into_iter().for_each(|e| self.parser_error(e));
+                return None;
+            }
+        };
+        let mut resolver = Resolver::new(interpreter);
+        let resolved = resolver.resolve(&program);
+        resolver
+            .take_warnings()
+            .into_iter()
+            .for_each(|w| self.resolver_warning(w));
+        if let Err(errors) = resolved {
+            errors.into_iter().for_each(|e| self.resolver_error(e));
+            return None;
+        }
+        Some(program)
+    }
+
+    /// Every error and warning reported since this `Lox` was created, or in the REPL, for the
+    /// latest entry, in the order they were found
+    /// They are also printed on stderr, this is for hosts that present them again, such as editors
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    fn scan_error(&mut self, err: ScanError) {
+        self.report(err.span.line, err.span.column, "", &err.message);
+        self.diagnostics.push(err.into());
+    }
+
+    fn syntax_error(&mut self, token: Token, message: &str) {
+        let position = match token.token_type() {
+            TokenType::Eof => " at end".to_string(),
+            _ => format!(" at '{}'", token.lexeme()),
+        };
+        self.report(token.line(), token.column(), &position, message);
+    }
+
+    fn parser_error(&mut self, err: ParserError) {
+        self.syntax_error(err.token().clone(), err.message());
+        self.diagnostics.push(err.into());
+    }
+
+    fn resolver_error(&mut self, err: ResolverError) {
+        self.syntax_error(err.token().clone(), &err.message());
+        self.diagnostics.push(err.into());
+    }
+
+    /// Printed like errors, but the program still runs
+    fn resolver_warning(&mut self, warning: ResolverWarning) {
+        let token = warning.token();
+        if self.print_errors {
+            eprintln!(
+                "[line {}:{}] Warning at '{}': {}",
+                token.line(),
+                token.column(),
+                token.lexeme(),
+                warning.message()
+            );
+        }
+        self.diagnostics.push(warning.into());
+    }
+
+    fn runtime_error(&mut self, runtime_err: RuntimeError) {
+        if self.print_errors {
+            eprintln!("{}", runtime_err.to_err_msg());
+        }
+        self.had_runtime_error = true;
+        self.diagnostics.push(runtime_err.into());
+    }
+
+    fn report(&mut self, line: usize, column: usize, position: &str, message: &str) {
+        if self.print_errors {
+            eprintln!("[line {line}:{column}] Error{position}: {message}");
+        }
+        self.had_error = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Output the REPL writes, kept readable after the interpreter takes the writer
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs each line as if entered at the REPL, returning what was echoed or printed
+    fn repl(lines: &[&str]) -> String {
+        let out = Output::default();
+        let mut interpreter = Interpreter::with_writer(Box::new(out.clone()));
+        let mut lox = Lox::silent();
+        for line in lines {
+            lox.run_repl(&mut interpreter, line.to_string());
+            assert!(!lox.had_error, "{line:?} should parse");
+        }
+        let out = out.0.borrow();
+        String::from_utf8(out.clone()).unwrap()
+    }
+
+    /// Asserts the REPL would keep reading until the last of `lines`, then runs them as one entry
+    fn continued(lines: &[&str]) -> String {
+        let mut source = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            source.push_str(line);
+            source.push('\n');
+            let last = i == lines.len() - 1;
+            assert_eq!(is_incomplete(&source), !last, "{source:?}");
+        }
+        repl(&[&source])
+    }
+
+    #[test]
+    fn errors_are_flagged_after_running_a_bad_program() {
+        let mut interpreter = Interpreter::with_writer(Box::new(io::sink()));
+        let mut lox = Lox::silent();
+        lox.run(&mut interpreter, "print (1;".to_string());
+        assert!(lox.had_error);
+        assert!(!lox.had_runtime_error);
+
+        let mut lox = Lox::silent();
+        lox.run(&mut interpreter, "print nil + 1;".to_string());
+        assert!(!lox.had_error);
+        assert!(lox.had_runtime_error);
+    }
+
+    #[test]
+    fn repl_forgets_errors_from_earlier_entries() {
+        let mut interpreter = Interpreter::with_writer(Box::new(io::sink()));
+        let mut lox = Lox::silent();
+        lox.run_repl(&mut interpreter, "print (1;".to_string());
+        assert!(lox.had_error);
+        assert_eq!(lox.diagnostics().len(), 1);
+        lox.run_repl(&mut interpreter, "print nil + 1;".to_string());
+        assert!(!lox.had_error);
+        assert!(lox.had_runtime_error);
+        lox.run_repl(&mut interpreter, "print 1;".to_string());
+        assert!(!lox.had_runtime_error);
+        assert!(lox.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn repl_echoes_bare_expressions() {
+        assert_eq!(repl(&["2*3"]), "6\n");
+    }
+
+    #[test]
+    fn repl_echoes_a_final_expression_statement() {
+        assert_eq!(repl(&["1 + 1;"]), "2\n");
+        assert_eq!(repl(&["print 1; 1 + 1;"]), "1\n2\n");
+    }
+
+    #[test]
+    fn repl_keeps_variables_between_lines() {
+        assert_eq!(repl(&["var x = 5;", "x"]), "5\n");
+    }
+
+    #[test]
+    fn repl_accepts_a_last_statement_without_a_semicolon() {
+        assert_eq!(repl(&["var x = 5", "x"]), "5\n");
+        assert_eq!(repl(&["print 1; print 2"]), "1\n2\n");
+        assert!(!is_incomplete("var x = 5"));
+    }
+
+    #[test]
+    fn repl_treats_empty_braces_as_a_block() {
+        assert_eq!(repl(&["{}"]), "");
+        assert_eq!(repl(&["{\"a\": 1}"]), "{a: 1}\n");
+    }
+
+    #[test]
+    fn repl_waits_for_the_rest_of_unfinished_input() {
+        assert!(is_incomplete("{ var x = 5;"));
+        assert!(is_incomplete("var x ="));
+        assert!(is_incomplete("print \"abc"));
+        assert!(!is_incomplete("print (1;"));
+    }
+
+    #[test]
+    fn repl_continues_a_multi_line_block() {
+        assert_eq!(
+            continued(&["if (true) {", "  print 1;", "  print 2;", "}"]),
+            "1\n2\n"
+        );
+    }
+
+    #[test]
+    fn repl_continues_a_multi_line_function() {
+        assert_eq!(
+            continued(&[
+                "fun add(a,",
+                "        b) {",
+                "  return a + b;",
+                "} print add(1, 2);"
+            ]),
+            "3\n"
+        );
+    }
+
+    #[test]
+    fn repl_does_not_wait_after_a_genuine_error() {
+        assert!(is_incomplete("while (true) {\n"));
+        assert!(!is_incomplete("while (true) {\n  print 1 +;\n"));
+        assert!(!is_incomplete("fun f(1) {\n"));
+    }
+}
@@ -1,25 +1,35 @@
-use crate::ast::{Expr, Stmt};
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+
+use crate::ast::{Expr, ExprId, FunctionDecl, Literal, Stmt};
 use crate::scanner::token::Token;
 use crate::scanner::token_type::TokenType;
-use crate::Lox;
 
-pub struct Parser<'a> {
-    lox: &'a mut Lox,
+/// Most arguments a call can pass, and most parameters a function can declare
+const MAX_ARITY: usize = 255;
+
+pub struct Parser {
     tokens: Vec<Token>,
+    errors: Vec<ParserError>,
+    // Set by `parse_repl`, where the last statement may leave out its ';'
+    optional_final_semicolon: bool,
 
     current: usize,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(lox: &'a mut Lox, tokens: Vec<Token>) -> Self {
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
         Parser {
-            lox,
             tokens,
+            errors: Vec::new(),
+            optional_final_semicolon: false,
             current: 0,
         }
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    /// Parses every declaration, recovering after errors so that all of them are collected
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParserError>> {
         let mut stmts = Vec::new();
         while self.peek().is_some() {
             match self.declaration() {
@@ -32,7 +42,42 @@ impl<'a> Parser<'a> {
                 }
             }
         }
-        stmts
+        if self.errors.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Same as `parse`, but also accepts a lone expression without a trailing ';',
+    /// and the last statement may leave out its ';' too, as in `var x = 5`
+    /// A final expression statement is returned as a print statement, so the REPL echoes its value
+    pub fn parse_repl(&mut self) -> Result<Vec<Stmt>, Vec<ParserError>> {
+        // `{}` is an empty block, as it would be in a script, rather than an empty map
+        let is_empty_block =
+            |expr: &Expr| matches!(expr, Expr::MapLiteral { entries, .. } if entries.is_empty());
+        let lone_expr = match self.expression() {
+            Ok(expr) if self.peek().is_none() && !is_empty_block(&expr) => Some(expr),
+            _ => None,
+        };
+        let mut program = match lone_expr {
+            Some(_) if !self.errors.is_empty() => return Err(std::mem::take(&mut self.errors)),
+            Some(expr) => vec![Stmt::Expr { expr }],
+            None => {
+                self.current = 0;
+                self.errors.clear();
+                self.optional_final_semicolon = true;
+                self.parse()?
+            }
+        };
+        if let Some(Stmt::Expr { expr }) = program.pop_if(|stmt| matches!(stmt, Stmt::Expr { .. }))
+        {
+            program.push(Stmt::Print {
+                keyword: self.previous().to_owned(),
+                expr,
+            });
+        }
+        Ok(program)
     }
 
     fn synchronize(&mut self) {
@@ -56,6 +101,7 @@ impl<'a> Parser<'a> {
                         | TokenType::For
                         | TokenType::If
                         | TokenType::While
+                        | TokenType::Switch
                         | TokenType::Print
                         | TokenType::Return
                 )
@@ -70,6 +116,11 @@ impl<'a> Parser<'a> {
     fn declaration(&mut self) -> Result<Stmt, ParserError> {
         if self.match_next(TokenType::Var) {
             self.var_decl()
+        } else if self.starts_fun_decl() {
+            self.current += 1;
+            self.fun_decl()
+        } else if self.match_next(TokenType::Class) {
+            self.class_decl()
         } else {
             self.statement()
         }
@@ -83,13 +134,116 @@ impl<'a> Parser<'a> {
         } else {
             None
         };
-        self.expect_next(TokenType::Semicolon)?;
+        self.expect_statement_end()?;
         Ok(Stmt::VarDecl {
             var_name,
             initializer,
         })
     }
 
+    /// `fun` followed by a name declares a function, otherwise it starts a lambda expression
+    fn starts_fun_decl(&self) -> bool {
+        matches!(self.current().token_type(), TokenType::Fun)
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|t| matches!(t.token_type(), TokenType::Identifier))
+    }
+
+    fn fun_decl(&mut self) -> Result<Stmt, ParserError> {
+        let decl = Rc::new(self.function()?);
+        Ok(Stmt::Function { decl })
+    }
+
+    fn class_decl(&mut self) -> Result<Stmt, ParserError> {
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.previous().to_owned();
+
+        let superclass = if self.match_next(TokenType::Less) {
+            self.consume(TokenType::Identifier, ParserError::ExpectSuperclassName)?;
+            Some(Expr::Variable {
+                name: self.previous().to_owned(),
+                id: ExprId::fresh(),
+            })
+        } else {
+            None
+        };
+
+        self.expect_next(TokenType::LeftBrace)?;
+        let mut methods = Vec::new();
+        let mut static_methods = Vec::new();
+        while self
+            .peek()
+            .is_some_and(|t| !matches!(t.token_type(), TokenType::RightBrace))
+        {
+            if self.match_next(TokenType::Class) {
+                static_methods.push(Rc::new(self.method()?));
+            } else {
+                methods.push(Rc::new(self.method()?));
+            }
+        }
+        self.expect_next(TokenType::RightBrace)?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+            static_methods,
+        })
+    }
+
+    /// Parses the name, parameters and body of a function or method
+    fn function(&mut self) -> Result<FunctionDecl, ParserError> {
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.previous().to_owned();
+        self.function_rest(name)
+    }
+
+    /// Parses a method, which is a getter if its name is followed directly by its body
+    fn method(&mut self) -> Result<FunctionDecl, ParserError> {
+        self.expect_next(TokenType::Identifier)?;
+        let name = self.previous().to_owned();
+        if !self.match_next(TokenType::LeftBrace) {
+            return self.function_rest(name);
+        }
+        let body = self.block()?;
+        Ok(FunctionDecl {
+            name,
+            params: Vec::new(),
+            body,
+            is_getter: true,
+        })
+    }
+
+    /// Parses the parameters and body of a function, after its name
+    fn function_rest(&mut self, name: Token) -> Result<FunctionDecl, ParserError> {
+        self.expect_next(TokenType::LeftParen)?;
+        let mut params = Vec::new();
+        if !self.match_next(TokenType::RightParen) {
+            loop {
+                if params.len() == MAX_ARITY {
+                    // Reported without bailing out, the parameters are still well-formed
+                    self.error(ParserError::TooManyParameters(self.current().to_owned()));
+                }
+                self.expect_next(TokenType::Identifier)?;
+                params.push(self.previous().to_owned());
+                if !self.match_next(TokenType::Comma) {
+                    break;
+                }
+            }
+            self.expect_next(TokenType::RightParen)?;
+        }
+
+        self.expect_next(TokenType::LeftBrace)?;
+        let body = self.block()?;
+        Ok(FunctionDecl {
+            name,
+            params,
+            body,
+            is_getter: false,
+        })
+    }
+
     fn statement(&mut self) -> Result<Stmt, ParserError> {
         if self.match_next(TokenType::Print) {
             self.print_stmt()
@@ -101,23 +255,43 @@ impl<'a> Parser<'a> {
             self.while_stmt()
         } else if self.match_next(TokenType::For) {
             self.for_stmt()
+        } else if self.match_next(TokenType::Switch) {
+            self.switch_stmt()
+        } else if self.match_next(TokenType::Break) {
+            self.break_stmt()
+        } else if self.match_next(TokenType::Continue) {
+            self.continue_stmt()
+        } else if self.match_next(TokenType::Return) {
+            self.return_stmt()
         } else {
             self.expr_stmt()
         }
     }
 
     fn print_stmt(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().to_owned();
         let expr = self.expression()?;
-        self.expect_next(TokenType::Semicolon)?;
-        Ok(Stmt::Print { expr: expr.into() })
+        self.expect_statement_end()?;
+        Ok(Stmt::Print { keyword, expr })
     }
 
     fn block_stmt(&mut self) -> Result<Stmt, ParserError> {
+        let stmt_list = self.block()?;
+        Ok(Stmt::Block { stmt_list })
+    }
+
+    /// Parses the declarations of a block up to and including the closing '}'
+    fn block(&mut self) -> Result<Vec<Stmt>, ParserError> {
+        let stmt_list = self.declarations_until(|t| matches!(t, TokenType::RightBrace));
+        self.expect_next(TokenType::RightBrace)?;
+        Ok(stmt_list)
+    }
+
+    /// Parses declarations up to, but not including, a token that `is_end` accepts
+    /// Errors are recorded and parsing carries on after them
+    fn declarations_until(&mut self, is_end: fn(&TokenType) -> bool) -> Vec<Stmt> {
         let mut stmt_list = Vec::new();
-        while self
-            .peek()
-            .is_some_and(|t| !matches!(t.token_type(), TokenType::RightBrace))
-        {
+        while self.peek().is_some_and(|t| !is_end(&t.token_type())) {
             match self.declaration() {
                 Ok(stmt) => stmt_list.push(stmt),
                 Err(err) => {
@@ -128,17 +302,17 @@ impl<'a> Parser<'a> {
                 }
             }
         }
-
-        self.expect_next(TokenType::RightBrace)?;
-        Ok(Stmt::Block { stmt_list })
+        stmt_list
     }
 
     fn expr_stmt(&mut self) -> Result<Stmt, ParserError> {
         let expr = self.expression()?;
-        self.expect_next(TokenType::Semicolon)?;
-        Ok(Stmt::Expr { expr: expr.into() })
+        self.expect_statement_end()?;
+        Ok(Stmt::Expr { expr })
     }
 
+    /// A dangling `else` binds to the nearest `if`, since a nested `if` in `then_stmt`
+    /// claims it first, and `else if` chains nest in `else_stmt`
     fn if_stmt(&mut self) -> Result<Stmt, ParserError> {
         let condition = self.expression()?;
         let then_stmt = Box::new(self.statement()?);
@@ -156,13 +330,22 @@ impl<'a> Parser<'a> {
     }
 
     fn while_stmt(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().to_owned();
+        self.expect_next(TokenType::LeftParen)?;
         let condition = self.expression()?;
+        self.expect_next(TokenType::RightParen)?;
         let body = Box::new(self.statement()?);
 
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            keyword,
+            condition,
+            body,
+            increment: None,
+        })
     }
 
     fn for_stmt(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().to_owned();
         self.expect_next(TokenType::LeftParen)?;
         let initializer = match self.current().token_type() {
             TokenType::Semicolon => {
@@ -178,7 +361,7 @@ impl<'a> Parser<'a> {
 
         let condition = match self.current().token_type() {
             TokenType::Semicolon => Expr::Literal {
-                value: Token::new(TokenType::True, "true".to_string(), self.current().line()),
+                value: Literal::Bool(true),
             },
             _ => self.expression()?,
         };
@@ -186,41 +369,98 @@ impl<'a> Parser<'a> {
 
         let increment = match self.current().token_type() {
             TokenType::RightParen => None,
-            _ => Some(Stmt::Expr {
-                expr: self.expression()?,
-            }),
+            _ => Some(self.expression()?),
         };
         self.expect_next(TokenType::RightParen)?;
 
         let body = self.statement()?;
 
-        Ok(Stmt::Block {
-            stmt_list: self.desugar_for_loop(initializer, condition, increment, body),
-        })
+        Ok(self.desugar_for_loop(keyword, initializer, condition, increment, body))
     }
 
+    /// Rewrites a `for` loop into a `while` loop:
+    /// `{ initializer; while (condition) body }`, with the increment run after each iteration
+    /// The outer block is only introduced when there is an initializer, and keeps the loop
+    /// variable from outliving the loop
+    /// As in the reference implementation, all iterations share that one variable,
+    /// so closures created in the body all see its final value
     fn desugar_for_loop(
         &self,
+        keyword: Token,
         initializer: Option<Stmt>,
         condition: Expr,
-        increment: Option<Stmt>,
+        increment: Option<Expr>,
         body: Stmt,
-    ) -> Vec<Stmt> {
-        let mut stmt_list = Vec::new();
-        if let Some(initializer) = initializer {
-            stmt_list.push(initializer);
-        }
-        let while_stmt_list = std::iter::once(body)
-            .chain(increment.into_iter())
-            .collect::<Vec<_>>();
+    ) -> Stmt {
         let while_stmt = Stmt::While {
+            keyword,
             condition,
-            body: Box::new(Stmt::Block {
-                stmt_list: while_stmt_list,
-            }),
+            body: Box::new(body),
+            increment,
         };
-        stmt_list.push(while_stmt);
-        stmt_list
+        match initializer {
+            Some(initializer) => Stmt::Block {
+                stmt_list: vec![initializer, while_stmt],
+            },
+            None => while_stmt,
+        }
+    }
+
+    /// `switch (discriminant) { case value: stmts ... default: stmts }`,
+    /// where the default case is optional and has to come last
+    fn switch_stmt(&mut self) -> Result<Stmt, ParserError> {
+        self.expect_next(TokenType::LeftParen)?;
+        let discriminant = self.expression()?;
+        self.expect_next(TokenType::RightParen)?;
+        self.expect_next(TokenType::LeftBrace)?;
+
+        let is_case_end = |t: &TokenType| {
+            matches!(
+                t,
+                TokenType::Case | TokenType::Default | TokenType::RightBrace
+            )
+        };
+        let mut cases = Vec::new();
+        while self.match_next(TokenType::Case) {
+            let value = self.expression()?;
+            self.expect_next(TokenType::Colon)?;
+            cases.push((value, self.declarations_until(is_case_end)));
+        }
+        let default = if self.match_next(TokenType::Default) {
+            self.expect_next(TokenType::Colon)?;
+            Some(self.declarations_until(is_case_end))
+        } else {
+            None
+        };
+        self.expect_next(TokenType::RightBrace)?;
+
+        Ok(Stmt::Switch {
+            discriminant,
+            cases,
+            default,
+        })
+    }
+
+    fn break_stmt(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().to_owned();
+        self.expect_statement_end()?;
+        Ok(Stmt::Break { keyword })
+    }
+
+    fn continue_stmt(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().to_owned();
+        self.expect_statement_end()?;
+        Ok(Stmt::Continue { keyword })
+    }
+
+    fn return_stmt(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().to_owned();
+        let value = match self.current().token_type() {
+            TokenType::Semicolon => None,
+            _ => Some(self.expression()?),
+        };
+        self.expect_statement_end()?;
+        Ok(Stmt::Return { keyword, value })
     }
 
     fn expression(&mut self) -> Result<Expr, ParserError> {
@@ -228,14 +468,36 @@ impl<'a> Parser<'a> {
     }
 
     fn assignment(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.or()?;
+        let mut expr = self.ternary()?;
         if self.match_next(TokenType::Equal) {
             match expr {
-                Expr::Variable { name } => {
+                Expr::Variable { name, .. } => {
                     let value = self.assignment()?;
                     expr = Expr::Assignment {
                         var_name: name,
                         value: value.into(),
+                        id: ExprId::fresh(),
+                    };
+                }
+                Expr::Get { object, name } => {
+                    let value = self.assignment()?;
+                    expr = Expr::Set {
+                        object,
+                        name,
+                        value: value.into(),
+                    };
+                }
+                Expr::Index {
+                    collection,
+                    bracket,
+                    index,
+                } => {
+                    let value = self.assignment()?;
+                    expr = Expr::IndexSet {
+                        collection,
+                        bracket,
+                        index,
+                        value: value.into(),
                     };
                 }
                 _ => {
@@ -243,10 +505,70 @@ impl<'a> Parser<'a> {
                     return Err(ParserError::InvalidAssignmentTarget(equals));
                 }
             }
+        } else if let Some(operator) = self.compound_assignment_operator() {
+            // `a += b` is sugar for `a = a + b`
+            let Expr::Variable { name, .. } = expr else {
+                return Err(ParserError::InvalidAssignmentTarget(
+                    self.previous().to_owned(),
+                ));
+            };
+            let value = self.assignment()?;
+            expr = Expr::Assignment {
+                var_name: name.clone(),
+                value: Expr::Binary {
+                    left: Expr::Variable {
+                        name,
+                        id: ExprId::fresh(),
+                    }
+                    .into(),
+                    operator,
+                    right: value.into(),
+                }
+                .into(),
+                id: ExprId::fresh(),
+            };
         }
         Ok(expr)
     }
 
+    /// Consumes a compound assignment operator such as `+=`,
+    /// returning the binary operator it applies, e.g. `+`
+    fn compound_assignment_operator(&mut self) -> Option<Token> {
+        let (token_type, lexeme) = match self.current().token_type() {
+            TokenType::PlusEqual => (TokenType::Plus, "+"),
+            TokenType::MinusEqual => (TokenType::Minus, "-"),
+            TokenType::StarEqual => (TokenType::Star, "*"),
+            TokenType::SlashEqual => (TokenType::Slash, "/"),
+            _ => return None,
+        };
+        let current = self.current();
+        let operator = Token::new(
+            token_type,
+            lexeme.to_string(),
+            current.line(),
+            current.column(),
+            current.span().start,
+        );
+        self.current += 1;
+        Some(operator)
+    }
+
+    /// Right-associative, so `a ? b : c ? d : e` groups as `a ? b : (c ? d : e)`
+    fn ternary(&mut self) -> Result<Expr, ParserError> {
+        let condition = self.or()?;
+        if !self.match_next(TokenType::Question) {
+            return Ok(condition);
+        }
+        let then_branch = self.expression()?;
+        self.expect_next(TokenType::Colon)?;
+        let else_branch = self.ternary()?;
+        Ok(Expr::Ternary {
+            condition: condition.into(),
+            then_branch: then_branch.into(),
+            else_branch: else_branch.into(),
+        })
+    }
+
     fn or(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.and()?;
         while self.match_next(TokenType::Or) {
@@ -276,8 +598,51 @@ impl<'a> Parser<'a> {
     }
 
     fn equality(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.comparison()?;
+        let mut expr = self.bitwise_or()?;
         while self.match_next(TokenType::EqualEqual) || self.match_next(TokenType::BangEqual) {
+            let operator = self.previous().to_owned();
+            let right = self.bitwise_or()?;
+            expr = Expr::Binary {
+                left: expr.into(),
+                operator,
+                right: right.into(),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// Bitwise operators bind looser than comparisons, so `a & b < c` groups as `a & (b < c)`
+    fn bitwise_or(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.bitwise_xor()?;
+        while self.match_next(TokenType::Pipe) {
+            let operator = self.previous().to_owned();
+            let right = self.bitwise_xor()?;
+            expr = Expr::Binary {
+                left: expr.into(),
+                operator,
+                right: right.into(),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn bitwise_xor(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.bitwise_and()?;
+        while self.match_next(TokenType::Caret) {
+            let operator = self.previous().to_owned();
+            let right = self.bitwise_and()?;
+            expr = Expr::Binary {
+                left: expr.into(),
+                operator,
+                right: right.into(),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn bitwise_and(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.comparison()?;
+        while self.match_next(TokenType::Ampersand) {
             let operator = self.previous().to_owned();
             let right = self.comparison()?;
             expr = Expr::Binary {
@@ -289,13 +654,33 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// `a < b < c` would compare a bool with `c`, so it is reported instead of parsed
     fn comparison(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.term()?;
+        let mut expr = self.shift()?;
+        let mut chained = false;
         while self.match_next(TokenType::Greater)
             || self.match_next(TokenType::GreaterEqual)
             || self.match_next(TokenType::Less)
             || self.match_next(TokenType::LessEqual)
         {
+            let operator = self.previous().to_owned();
+            if chained {
+                self.error(ParserError::ChainedComparison(operator.clone()));
+            }
+            chained = true;
+            let right = self.shift()?;
+            expr = Expr::Binary {
+                left: expr.into(),
+                operator,
+                right: right.into(),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.term()?;
+        while self.match_next(TokenType::LessLess) || self.match_next(TokenType::GreaterGreater) {
             let operator = self.previous().to_owned();
             let right = self.term()?;
             expr = Expr::Binary {
@@ -323,7 +708,10 @@ impl<'a> Parser<'a> {
 
     fn factor(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.unary()?;
-        while self.match_next(TokenType::Star) || self.match_next(TokenType::Slash) {
+        while self.match_next(TokenType::Star)
+            || self.match_next(TokenType::Slash)
+            || self.match_next(TokenType::Percent)
+        {
             let operator = self.previous().to_owned();
             let right = self.unary()?;
             expr = Expr::Binary {
@@ -336,7 +724,10 @@ impl<'a> Parser<'a> {
     }
 
     fn unary(&mut self) -> Result<Expr, ParserError> {
-        if self.match_next(TokenType::Bang) || self.match_next(TokenType::Minus) {
+        if self.match_next(TokenType::Bang)
+            || self.match_next(TokenType::Minus)
+            || self.match_next(TokenType::Tilde)
+        {
             let operator = self.previous().to_owned();
             let right = self.unary()?;
             let expr = Expr::Unary {
@@ -344,24 +735,121 @@ impl<'a> Parser<'a> {
                 right: right.into(),
             };
             Ok(expr)
+        } else if self.match_next(TokenType::PlusPlus) || self.match_next(TokenType::MinusMinus) {
+            let operator = self.previous().to_owned();
+            let target = self.unary()?;
+            Ok(self.increment(target, operator, true))
         } else {
-            self.primary()
+            self.postfix()
         }
     }
 
+    fn postfix(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.call()?;
+        if self.match_next(TokenType::PlusPlus) || self.match_next(TokenType::MinusMinus) {
+            let operator = self.previous().to_owned();
+            return Ok(self.increment(expr, operator, false));
+        }
+        Ok(expr)
+    }
+
+    /// Only variables can be incremented or decremented, like with compound assignment
+    /// Any other target is reported without bailing out, and is kept as is
+    fn increment(&mut self, target: Expr, operator: Token, prefix: bool) -> Expr {
+        let Expr::Variable { name, .. } = target else {
+            self.error(ParserError::InvalidAssignmentTarget(operator));
+            return target;
+        };
+        Expr::Increment {
+            var_name: name,
+            operator,
+            prefix,
+            id: ExprId::fresh(),
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.match_next(TokenType::LeftParen) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_next(TokenType::Dot) {
+                self.consume(TokenType::Identifier, ParserError::ExpectPropertyName)?;
+                expr = Expr::Get {
+                    object: expr.into(),
+                    name: self.previous().to_owned(),
+                };
+            } else if self.match_next(TokenType::LeftBracket) {
+                let index = self.expression()?;
+                self.expect_next(TokenType::RightBracket)?;
+                expr = Expr::Index {
+                    collection: expr.into(),
+                    bracket: self.previous().to_owned(),
+                    index: index.into(),
+                };
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParserError> {
+        let mut arguments = Vec::new();
+        if !self.match_next(TokenType::RightParen) {
+            loop {
+                if arguments.len() == MAX_ARITY {
+                    self.error(ParserError::TooManyArguments(self.current().to_owned()));
+                }
+                arguments.push(self.expression()?);
+                if !self.match_next(TokenType::Comma) {
+                    break;
+                }
+            }
+            self.expect_next(TokenType::RightParen)?;
+        }
+        let paren = self.previous().to_owned();
+
+        Ok(Expr::Call {
+            callee: callee.into(),
+            paren,
+            arguments,
+        })
+    }
+
     fn primary(&mut self) -> Result<Expr, ParserError> {
-        if self.match_next(TokenType::String("".to_string()))
-            || self.match_next(TokenType::Number(0.0))
-            || self.match_next(TokenType::True)
-            || self.match_next(TokenType::False)
-            || self.match_next(TokenType::Nil)
-        {
-            Ok(Expr::Literal {
-                value: self.previous().to_owned(),
+        let literal = match self.current().token_type() {
+            TokenType::Nil => Some(Literal::Nil),
+            TokenType::True => Some(Literal::Bool(true)),
+            TokenType::False => Some(Literal::Bool(false)),
+            TokenType::Number(n) => Some(Literal::Number(n)),
+            TokenType::String(s) => Some(Literal::String(s.into())),
+            _ => None,
+        };
+        if let Some(value) = literal {
+            self.current += 1;
+            Ok(Expr::Literal { value })
+        } else if self.match_next(TokenType::Super) {
+            let keyword = self.previous().to_owned();
+            self.expect_next(TokenType::Dot)?;
+            self.consume(
+                TokenType::Identifier,
+                ParserError::ExpectSuperclassMethodName,
+            )?;
+            Ok(Expr::Super {
+                keyword,
+                method: self.previous().to_owned(),
+                id: ExprId::fresh(),
+            })
+        } else if self.match_next(TokenType::This) {
+            Ok(Expr::This {
+                keyword: self.previous().to_owned(),
+                id: ExprId::fresh(),
             })
         } else if self.match_next(TokenType::Identifier) {
             Ok(Expr::Variable {
                 name: self.previous().to_owned(),
+                id: ExprId::fresh(),
             })
         } else if self.match_next(TokenType::LeftParen) {
             let expr = self.expression()?;
@@ -369,20 +857,58 @@ impl<'a> Parser<'a> {
             Ok(Expr::Grouping {
                 expression: expr.into(),
             })
+        } else if self.match_next(TokenType::Fun) {
+            let keyword = self.previous().to_owned();
+            let decl = self.function_rest(keyword)?;
+            Ok(Expr::Lambda {
+                decl: Rc::new(decl),
+            })
+        } else if self.match_next(TokenType::LeftBracket) {
+            let mut elements = Vec::new();
+            if !self.match_next(TokenType::RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !self.match_next(TokenType::Comma) {
+                        break;
+                    }
+                }
+                self.expect_next(TokenType::RightBracket)?;
+            }
+            Ok(Expr::ListLiteral { elements })
+        } else if self.match_next(TokenType::LeftBrace) {
+            // Statements starting with `{` are blocks, so here it can only start a map
+            self.map_literal()
         } else {
             Err(ParserError::ExpectExpression(self.current().to_owned()))
         }
     }
 
-    // TODO: consider an `expect_next` method, equivalent to `consume` in the book
-    fn match_next(&mut self, expected_type: TokenType) -> bool {
-        let matching = |t: &Token| match (t.token_type(), expected_type) {
-            (TokenType::String(_), TokenType::String(_))
-            | (TokenType::Number(_), TokenType::Number(_)) => true,
-            (t1, t2) => t1 == t2,
-        };
+    fn map_literal(&mut self) -> Result<Expr, ParserError> {
+        let brace = self.previous().to_owned();
+        let mut entries = Vec::new();
+        if !self.match_next(TokenType::RightBrace) {
+            loop {
+                let key = self.expression()?;
+                self.expect_next(TokenType::Colon)?;
+                let value = self.expression()?;
+                entries.push((key, value));
+                if !self.match_next(TokenType::Comma) {
+                    break;
+                }
+            }
+            self.expect_next(TokenType::RightBrace)?;
+        }
+        Ok(Expr::MapLiteral { brace, entries })
+    }
 
-        if self.peek().is_some_and(matching) {
+    /// Consumes the next token if it has the expected type
+    /// Literal payloads are ignored, so `TokenType::Number(0.0)` matches any number
+    fn match_next(&mut self, expected_type: TokenType) -> bool {
+        let expected = mem::discriminant(&expected_type);
+        if self
+            .peek()
+            .is_some_and(|t| mem::discriminant(&t.token_type()) == expected)
+        {
             self.current += 1;
             true
         } else {
@@ -390,22 +916,43 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Consumes and return Ok(()) if the next token matches the expected type,
-    /// Otherwise, return ParserError corresponding to the expected type
-    /// Panics if `expected_type` does not correspond to any `ParserError`
+    /// Consumes a token of `expected_type`, reporting the usual error for that type otherwise
+    /// Panics for token types without one, use `consume` to supply the error for those
     fn expect_next(&mut self, expected_type: TokenType) -> Result<(), ParserError> {
         let err = match expected_type {
-            TokenType::LeftParen => ParserError::ExpectLeftParen(self.current().to_owned()),
-            TokenType::RightParen => ParserError::ExpectRightParen(self.current().to_owned()),
-            TokenType::RightBrace => ParserError::ExpectRightBrace(self.current().to_owned()),
-            TokenType::Semicolon => ParserError::ExpectSemicolon(self.current().to_owned()),
-            TokenType::Identifier => ParserError::ExpectIdentifier(self.current().to_owned()),
+            TokenType::LeftParen => ParserError::ExpectLeftParen,
+            TokenType::RightParen => ParserError::ExpectRightParen,
+            TokenType::LeftBrace => ParserError::ExpectLeftBrace,
+            TokenType::Dot => ParserError::ExpectDot,
+            TokenType::Colon => ParserError::ExpectColon,
+            TokenType::RightBrace => ParserError::ExpectRightBrace,
+            TokenType::RightBracket => ParserError::ExpectRightBracket,
+            TokenType::Semicolon => ParserError::ExpectSemicolon,
+            TokenType::Identifier => ParserError::ExpectIdentifier,
             _ => panic!("expected_type of expect_next does not correspond to any parser error"),
         };
+        self.consume(expected_type, err)
+    }
+
+    /// Consumes the ';' ending a statement
+    /// In the REPL the last statement can leave it out, as in `var x = 5`
+    fn expect_statement_end(&mut self) -> Result<(), ParserError> {
+        if self.optional_final_semicolon && self.peek().is_none() {
+            return Ok(());
+        }
+        self.expect_next(TokenType::Semicolon)
+    }
+
+    /// Consumes a token of `expected_type`, otherwise builds the error from the current token
+    fn consume(
+        &mut self,
+        expected_type: TokenType,
+        err: fn(Token) -> ParserError,
+    ) -> Result<(), ParserError> {
         if self.match_next(expected_type) {
             Ok(())
         } else {
-            Err(err)
+            Err(err(self.current().to_owned()))
         }
     }
 
@@ -440,51 +987,270 @@ impl<'a> Parser<'a> {
     }
 
     fn error(&mut self, err: ParserError) {
-        match err {
-            ParserError::ExpectExpression(t) => {
-                self.lox.syntax_error(t, "Expect expression");
-            }
-            ParserError::ExpectLeftParen(t) => {
-                self.lox.syntax_error(t, "Expect '('");
-            }
-            ParserError::ExpectRightParen(t) => {
-                self.lox.syntax_error(t, "Expect ')'");
-            }
-            ParserError::ExpectRightBrace(t) => {
-                self.lox.syntax_error(t, "Expect '}'");
-            }
-            ParserError::ExpectSemicolon(t) => {
-                self.lox.syntax_error(t, "Expect ';'");
-            }
-            ParserError::ExpectIdentifier(t) => {
-                self.lox.syntax_error(t, "Expect identifier");
-            }
-            ParserError::InvalidAssignmentTarget(t) => {
-                self.lox.syntax_error(t, "Invalid assignment target");
-            }
-        }
+        self.errors.push(err);
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum ParserError {
     ExpectExpression(Token),
     ExpectLeftParen(Token),
     ExpectRightParen(Token),
+    ExpectDot(Token),
+    ExpectColon(Token),
+    ExpectLeftBrace(Token),
     ExpectRightBrace(Token),
+    ExpectRightBracket(Token),
     ExpectSemicolon(Token),
     ExpectIdentifier(Token),
+    ExpectSuperclassName(Token),
+    ExpectPropertyName(Token),
+    ExpectSuperclassMethodName(Token),
     InvalidAssignmentTarget(Token),
+    ChainedComparison(Token),
+    TooManyParameters(Token),
+    TooManyArguments(Token),
 }
 
 impl ParserError {
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::ExpectExpression(t)
+            | Self::ExpectLeftParen(t)
+            | Self::ExpectRightParen(t)
+            | Self::ExpectDot(t)
+            | Self::ExpectColon(t)
+            | Self::ExpectLeftBrace(t)
+            | Self::ExpectRightBrace(t)
+            | Self::ExpectRightBracket(t)
+            | Self::ExpectSemicolon(t)
+            | Self::ExpectIdentifier(t)
+            | Self::ExpectSuperclassName(t)
+            | Self::ExpectPropertyName(t)
+            | Self::ExpectSuperclassMethodName(t)
+            | Self::InvalidAssignmentTarget(t)
+            | Self::ChainedComparison(t)
+            | Self::TooManyParameters(t)
+            | Self::TooManyArguments(t) => t,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::ExpectExpression(_) => "Expect expression",
+            Self::ExpectLeftParen(_) => "Expect '('",
+            Self::ExpectRightParen(_) => "Expect ')'",
+            Self::ExpectDot(_) => "Expect '.'",
+            Self::ExpectColon(_) => "Expect ':'",
+            Self::ExpectLeftBrace(_) => "Expect '{'",
+            Self::ExpectRightBrace(_) => "Expect '}'",
+            Self::ExpectRightBracket(_) => "Expect ']'",
+            Self::ExpectSemicolon(_) => "Expect ';'",
+            Self::ExpectIdentifier(_) => "Expect identifier",
+            Self::ExpectSuperclassName(_) => "Expect superclass name",
+            Self::ExpectPropertyName(_) => "Expect property name after '.'",
+            Self::ExpectSuperclassMethodName(_) => "Expect superclass method name",
+            Self::InvalidAssignmentTarget(_) => "Invalid assignment target",
+            Self::ChainedComparison(_) => {
+                "Chained comparison is not allowed; use parentheses or `and`"
+            }
+            Self::TooManyParameters(_) => "Can't have more than 255 parameters",
+            Self::TooManyArguments(_) => "Can't have more than 255 arguments",
+        }
+    }
+
     fn should_panic(&self) -> bool {
         matches!(
             self,
             Self::ExpectExpression(_)
                 | Self::ExpectRightParen(_)
+                | Self::ExpectRightBracket(_)
                 | Self::ExpectSemicolon(_)
                 | Self::ExpectIdentifier(_)
+                | Self::ExpectSuperclassName(_)
+                | Self::ExpectPropertyName(_)
+                | Self::ExpectSuperclassMethodName(_)
         )
     }
 }
+
+/// Renders the error the way it is reported, e.g. `[line 1:9] Error at ';': Expect ')'`
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = self.token();
+        write!(f, "[line {}:{}] Error", token.line(), token.column())?;
+        match token.token_type() {
+            TokenType::Eof => f.write_str(" at end")?,
+            _ => write!(f, " at '{}'", token.lexeme())?,
+        }
+        write!(f, ": {}", self.message())
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn parser(source: &str) -> Parser {
+        Parser::new(Scanner::new(source.to_string()).scan_tokens().unwrap())
+    }
+
+    fn parse_errors(source: &str) -> Vec<ParserError> {
+        parser(source)
+            .parse()
+            .expect_err("source should fail to parse")
+    }
+
+    #[test]
+    fn consume_left_paren_with_a_custom_error() {
+        let mut parser = parser("( x");
+        assert!(parser
+            .consume(TokenType::LeftParen, ParserError::ExpectPropertyName)
+            .is_ok());
+        let err = parser
+            .consume(TokenType::LeftParen, ParserError::ExpectPropertyName)
+            .unwrap_err();
+        assert!(matches!(err, ParserError::ExpectPropertyName(_)));
+        assert_eq!(err.token().lexeme(), "x");
+    }
+
+    #[test]
+    fn consume_comma_with_a_custom_error() {
+        let mut parser = parser(", ;");
+        assert!(parser
+            .consume(TokenType::Comma, ParserError::ExpectSuperclassName)
+            .is_ok());
+        let err = parser
+            .consume(TokenType::Comma, ParserError::ExpectSuperclassName)
+            .unwrap_err();
+        assert!(matches!(err, ParserError::ExpectSuperclassName(_)));
+        assert_eq!(err.token().lexeme(), ";");
+    }
+
+    #[test]
+    fn var_and_if_report_the_usual_errors() {
+        assert!(matches!(
+            parse_errors("var = 1;")[..],
+            [ParserError::ExpectIdentifier(_)]
+        ));
+        assert!(matches!(
+            parse_errors("var x = 1")[..],
+            [ParserError::ExpectSemicolon(_)]
+        ));
+        assert!(matches!(
+            parse_errors("if ; print 1;")[..],
+            [ParserError::ExpectExpression(_)]
+        ));
+        assert!(matches!(
+            parse_errors("if (true print 1;")[..],
+            [ParserError::ExpectRightParen(_)]
+        ));
+    }
+
+    #[test]
+    fn while_reports_a_missing_left_paren() {
+        assert!(matches!(
+            parse_errors("while true) print 1;")[..],
+            [ParserError::ExpectLeftParen(_), ..]
+        ));
+    }
+
+    #[test]
+    fn call_sites_supply_their_own_errors() {
+        assert!(matches!(
+            parse_errors("class A < {}")[..],
+            [ParserError::ExpectSuperclassName(_)]
+        ));
+        assert!(matches!(
+            parse_errors("a.;")[..],
+            [ParserError::ExpectPropertyName(_)]
+        ));
+        assert!(matches!(
+            parse_errors("class A < B { f() { super.; } }")[..],
+            [ParserError::ExpectSuperclassMethodName(_)]
+        ));
+    }
+
+    #[test]
+    fn errors_are_returned_with_the_token_they_are_at() {
+        let errors = parse_errors("1 = 2;\nprint (3;\nvar 4;");
+        let found: Vec<_> = errors
+            .iter()
+            .map(|err| (err.message(), err.token().lexeme(), err.token().line()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Invalid assignment target", "=".to_string(), 1),
+                ("Expect ')'", ";".to_string(), 2),
+                ("Expect identifier", "4".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn chained_comparison_is_reported_at_the_second_operator() {
+        let errors = parse_errors("print 1 < 2 < 3;");
+        assert!(matches!(errors[..], [ParserError::ChainedComparison(_)]));
+        assert_eq!(errors[0].token().column(), 13);
+    }
+
+    #[test]
+    fn too_many_arguments_is_reported_at_the_256th_and_parsing_goes_on() {
+        let args: Vec<_> = (0..256).map(|i| format!("a{i}")).collect();
+        let errors = parse_errors(&format!("f({});\nprint (1;", args.join(", ")));
+        assert!(matches!(
+            errors[..],
+            [
+                ParserError::TooManyArguments(_),
+                ParserError::ExpectRightParen(_)
+            ]
+        ));
+        assert_eq!(errors[0].token().lexeme(), "a255");
+        assert_eq!(errors[1].token().line(), 2);
+    }
+
+    #[test]
+    fn too_many_parameters_is_reported_at_the_256th_and_parsing_goes_on() {
+        let params: Vec<_> = (0..256).map(|i| format!("p{i}")).collect();
+        let errors = parse_errors(&format!("fun f({}) {{}}\nprint (1;", params.join(", ")));
+        assert!(matches!(
+            errors[..],
+            [
+                ParserError::TooManyParameters(_),
+                ParserError::ExpectRightParen(_)
+            ]
+        ));
+        assert_eq!(errors[0].token().lexeme(), "p255");
+    }
+
+    #[test]
+    fn exactly_255_arguments_and_parameters_are_allowed() {
+        let names: Vec<_> = (0..255).map(|i| format!("a{i}")).collect();
+        let names = names.join(", ");
+        assert!(parser(&format!("fun f({names}) {{}} f({names});"))
+            .parse()
+            .is_ok());
+    }
+
+    #[test]
+    fn tokens_with_equal_fields_are_equal() {
+        let first = Scanner::new("x + x".to_string()).scan_tokens().unwrap();
+        let second = Scanner::new("x + x".to_string()).scan_tokens().unwrap();
+        assert_eq!(first, second);
+        // Same lexeme at a different column
+        assert_ne!(first[0], first[2]);
+    }
+
+    #[test]
+    fn match_next_ignores_the_value_of_strings_and_numbers() {
+        let mut parser = parser("\"a\" 1");
+        assert!(!parser.match_next(TokenType::Number(0.0)));
+        assert!(parser.match_next(TokenType::String(String::new())));
+        assert!(parser.match_next(TokenType::Number(0.0)));
+        assert!(!parser.match_next(TokenType::Number(0.0)));
+    }
+}
@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+
+use crate::ast::{Expr, ExprId, FunctionDecl, Stmt};
+use crate::interpreter::Interpreter;
+use crate::scanner::token::Token;
+
+/// Walks the program once before it is interpreted and tells the interpreter
+/// how many scopes away each local variable is declared
+/// Variables not found in any local scope are assumed to be global
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    scopes: Vec<HashMap<String, Local>>,
+    current_function: FunctionType,
+    // Whether `break` and `continue` have a loop to jump out of
+    in_loop: bool,
+    // The class whose methods `this` and `super` are bound in, which covers the functions
+    // declared in those methods too
+    current_class: ClassType,
+    errors: Vec<ResolverError>,
+    warnings: Vec<ResolverWarning>,
+}
+
+struct Local {
+    // Position of the variable in its scope, matching the order it is declared at runtime
+    slot: usize,
+    // Whether its initializer has been resolved
+    defined: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+#[derive(Clone, Debug)]
+pub enum ResolverError {
+    ReadLocalInOwnInitializer(Token),
+    AlreadyDeclared(Token),
+    ReturnValueFromInitializer(Token),
+    InitializerGetter(Token),
+    ReturnFromTopLevel(Token),
+    InheritFromSelf(Token),
+    OutsideLoop(Token),
+    ThisOutsideClass(Token),
+    SuperOutsideClass(Token),
+    SuperWithoutSuperclass(Token),
+}
+
+/// Likely mistakes that don't stop the program from running
+#[derive(Clone, Debug)]
+pub enum ResolverWarning {
+    // Reported at the `return`, `break` or `continue` that the code follows
+    UnreachableCode(Token),
+}
+
+impl ResolverWarning {
+    pub fn message(&self) -> String {
+        match self {
+            Self::UnreachableCode(t) => format!("Code after '{}' is unreachable", t.lexeme()),
+        }
+    }
+
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::UnreachableCode(t) => t,
+        }
+    }
+}
+
+impl ResolverError {
+    pub fn message(&self) -> String {
+        match self {
+            Self::ReadLocalInOwnInitializer(_) => {
+                "Can't read local variable in its own initializer".to_string()
+            }
+            Self::AlreadyDeclared(_) => {
+                "Already a variable with this name in this scope".to_string()
+            }
+            Self::ReturnValueFromInitializer(_) => {
+                "Can't return a value from an initializer".to_string()
+            }
+            Self::InitializerGetter(_) => "An initializer can't be a getter".to_string(),
+            Self::ReturnFromTopLevel(_) => "Can't return from top-level code".to_string(),
+            Self::InheritFromSelf(_) => "A class can't inherit from itself".to_string(),
+            Self::OutsideLoop(t) => format!("'{}' outside a loop", t.lexeme()),
+            Self::ThisOutsideClass(_) => "Can't use 'this' outside of a class".to_string(),
+            Self::SuperOutsideClass(_) => "Can't use 'super' outside of a class".to_string(),
+            Self::SuperWithoutSuperclass(_) => {
+                "Can't use 'super' in a class with no superclass".to_string()
+            }
+        }
+    }
+
+    /// The token the error is reported at
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::ReadLocalInOwnInitializer(t)
+            | Self::AlreadyDeclared(t)
+            | Self::ReturnValueFromInitializer(t)
+            | Self::InitializerGetter(t)
+            | Self::ReturnFromTopLevel(t)
+            | Self::InheritFromSelf(t)
+            | Self::OutsideLoop(t)
+            | Self::ThisOutsideClass(t)
+            | Self::SuperOutsideClass(t)
+            | Self::SuperWithoutSuperclass(t) => t,
+        }
+    }
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Resolver {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            in_loop: false,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Warnings found by `resolve`, which are reported whether or not it succeeds
+    pub fn take_warnings(&mut self) -> Vec<ResolverWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn resolve(&mut self, program: &[Stmt]) -> Result<(), Vec<ResolverError>> {
+        self.resolve_stmts(program);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn resolve_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.resolve_stmt(stmt);
+        }
+        // Only the first jump of a block can be reached, and only if it is the last statement
+        let jump = stmts.iter().position(|stmt| {
+            matches!(
+                stmt,
+                Stmt::Return { .. } | Stmt::Break { .. } | Stmt::Continue { .. }
+            )
+        });
+        if let Some(i) = jump.filter(|&i| i + 1 < stmts.len()) {
+            let keyword = match &stmts[i] {
+                Stmt::Return { keyword, .. }
+                | Stmt::Break { keyword }
+                | Stmt::Continue { keyword } => keyword.clone(),
+                _ => unreachable!("position only matches jumps"),
+            };
+            self.warnings
+                .push(ResolverWarning::UnreachableCode(keyword));
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr { expr } | Stmt::Print { expr, .. } => self.resolve_expr(expr),
+            Stmt::VarDecl {
+                var_name,
+                initializer,
+            } => {
+                self.declare(var_name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expr(initializer);
+                }
+                self.define(var_name);
+            }
+            Stmt::Block { stmt_list } => self.resolve_block(stmt_list),
+            Stmt::Switch {
+                discriminant,
+                cases,
+                default,
+            } => {
+                self.resolve_expr(discriminant);
+                for (value, stmt_list) in cases {
+                    self.resolve_expr(value);
+                    self.resolve_block(stmt_list);
+                }
+                if let Some(stmt_list) = default {
+                    self.resolve_block(stmt_list);
+                }
+            }
+            Stmt::If {
+                condition,
+                then_stmt,
+                else_stmt,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(then_stmt);
+                if let Some(else_stmt) = else_stmt {
+                    self.resolve_stmt(else_stmt);
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => {
+                self.resolve_expr(condition);
+                let enclosing_loop = std::mem::replace(&mut self.in_loop, true);
+                self.resolve_stmt(body);
+                self.in_loop = enclosing_loop;
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment);
+                }
+            }
+            Stmt::Break { keyword } | Stmt::Continue { keyword } => {
+                if !self.in_loop {
+                    self.errors
+                        .push(ResolverError::OutsideLoop(keyword.clone()));
+                }
+            }
+            Stmt::Function { decl } => {
+                // Defined eagerly so the function can refer to itself recursively
+                self.declare(&decl.name);
+                self.define(&decl.name);
+                self.resolve_function(decl, FunctionType::Function);
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                static_methods,
+            } => {
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable {
+                        name: super_name, ..
+                    } = superclass
+                    {
+                        if super_name.lexeme() == name.lexeme() {
+                            self.errors
+                                .push(ResolverError::InheritFromSelf(super_name.clone()));
+                        }
+                    }
+                    self.resolve_expr(superclass);
+
+                    // Subclass methods are bound in a scope holding `super`
+                    self.begin_scope();
+                    self.define_implicit("super");
+                }
+
+                // Methods are bound in a scope of their own that holds `this`
+                self.begin_scope();
+                self.define_implicit("this");
+                let class_type = if superclass.is_some() {
+                    ClassType::Subclass
+                } else {
+                    ClassType::Class
+                };
+                let enclosing_class = std::mem::replace(&mut self.current_class, class_type);
+                for method in methods {
+                    let function_type = if method.name.lexeme() == "init" {
+                        // Initializers take the arguments the class is called with, getters none
+                        if method.is_getter {
+                            self.errors
+                                .push(ResolverError::InitializerGetter(method.name.clone()));
+                        }
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    self.resolve_function(method, function_type);
+                }
+                self.current_class = enclosing_class;
+                self.end_scope();
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                // Static methods are bound outside both scopes, so have neither `this` nor `super`
+                let enclosing_class = std::mem::replace(&mut self.current_class, ClassType::None);
+                for method in static_methods {
+                    self.resolve_function(method, FunctionType::Function);
+                }
+                self.current_class = enclosing_class;
+            }
+            Stmt::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.errors
+                        .push(ResolverError::ReturnFromTopLevel(keyword.clone()));
+                }
+                if let Some(value) = value {
+                    // A bare `return;` is still allowed to exit an initializer early
+                    if self.current_function == FunctionType::Initializer {
+                        self.errors
+                            .push(ResolverError::ReturnValueFromInitializer(keyword.clone()));
+                    }
+                    self.resolve_expr(value);
+                }
+            }
+        }
+    }
+
+    fn resolve_block(&mut self, stmt_list: &[Stmt]) {
+        self.begin_scope();
+        self.resolve_stmts(stmt_list);
+        self.end_scope();
+    }
+
+    fn resolve_function(&mut self, decl: &FunctionDecl, function_type: FunctionType) {
+        let enclosing_function = std::mem::replace(&mut self.current_function, function_type);
+        // Loops don't extend into the functions declared in them
+        let enclosing_loop = std::mem::replace(&mut self.in_loop, false);
+        self.begin_scope();
+        for param in &decl.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_stmts(&decl.body);
+        self.end_scope();
+        self.current_function = enclosing_function;
+        self.in_loop = enclosing_loop;
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(then_branch);
+                self.resolve_expr(else_branch);
+            }
+            Expr::Grouping { expression } => self.resolve_expr(expression),
+            Expr::Literal { .. } => (),
+            Expr::Unary { right, .. } => self.resolve_expr(right),
+            Expr::Variable { name, id } => self.resolve_read(*id, name),
+            Expr::Assignment {
+                var_name,
+                value,
+                id,
+            } => {
+                self.resolve_expr(value);
+                self.resolve_local(*id, var_name);
+            }
+            // `++` and `--` read the variable before assigning to it
+            Expr::Increment { var_name, id, .. } => self.resolve_read(*id, var_name),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expr(callee);
+                for arg in arguments {
+                    self.resolve_expr(arg);
+                }
+            }
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::This { keyword, id } => {
+                if self.current_class == ClassType::None {
+                    self.errors
+                        .push(ResolverError::ThisOutsideClass(keyword.clone()));
+                }
+                self.resolve_local(*id, keyword)
+            }
+            Expr::Super { keyword, id, .. } => {
+                match self.current_class {
+                    ClassType::None => self
+                        .errors
+                        .push(ResolverError::SuperOutsideClass(keyword.clone())),
+                    ClassType::Class => self
+                        .errors
+                        .push(ResolverError::SuperWithoutSuperclass(keyword.clone())),
+                    ClassType::Subclass => (),
+                }
+                self.resolve_local(*id, keyword)
+            }
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::Lambda { decl } => self.resolve_function(decl, FunctionType::Function),
+            Expr::ListLiteral { elements } => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::MapLiteral { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Index {
+                collection, index, ..
+            } => {
+                self.resolve_expr(collection);
+                self.resolve_expr(index);
+            }
+            Expr::IndexSet {
+                collection,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expr(collection);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            }
+        }
+    }
+
+    /// Resolves a variable being read, which can't be done in its own initializer
+    fn resolve_read(&mut self, id: ExprId, name: &Token) {
+        let declared_only = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(&name.lexeme()))
+            .is_some_and(|local| !local.defined);
+        if declared_only {
+            self.errors
+                .push(ResolverError::ReadLocalInOwnInitializer(name.clone()));
+        }
+        self.resolve_local(id, name);
+    }
+
+    fn resolve_local(&mut self, id: ExprId, name: &Token) {
+        let resolved = self
+            .scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| Some((depth, scope.get(&name.lexeme())?.slot)));
+        if let Some((depth, slot)) = resolved {
+            self.interpreter.resolve(id, depth, slot);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes
+            .pop()
+            .expect("scopes should not be empty when popped");
+    }
+
+    /// Redeclaring a name is only an error in local scopes, globals can be redeclared freely
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.contains_key(&name.lexeme()) {
+                self.errors
+                    .push(ResolverError::AlreadyDeclared(name.clone()));
+                return;
+            }
+            let slot = scope.len();
+            scope.insert(
+                name.lexeme(),
+                Local {
+                    slot,
+                    defined: false,
+                },
+            );
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&name.lexeme()))
+        {
+            local.defined = true;
+        }
+    }
+
+    /// Defines a name the interpreter binds on its own, such as `this`
+    fn define_implicit(&mut self, name: &str) {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("scope should have been pushed");
+        let slot = scope.len();
+        scope.insert(
+            name.to_string(),
+            Local {
+                slot,
+                defined: true,
+            },
+        );
+    }
+}
@@ -3,48 +3,87 @@ use std::char;
 pub mod token;
 pub mod token_type;
 
-use crate::Lox;
+use unicode_xid::UnicodeXID;
+
+use crate::diagnostic::Span;
 use token::Token;
 use token_type::TokenType;
 
-pub struct Scanner<'a> {
-    lox: &'a mut Lox,
+/// Scans tokens lazily, as an iterator over each token or error in the source
+/// The last token is always `Eof`
+pub struct Scanner {
     source: String,
-    tokens: Vec<Token>,
+    // Whether the `Eof` token has been produced
+    done: bool,
 
+    // Byte offsets into `source`, always on char boundaries
     start: usize,
     current: usize,
     line: usize,
+    // Column of the char at `current`, kept up to date as chars are consumed
+    column: usize,
+    // Position of the token being scanned, tokens spanning lines are placed where they start
+    start_line: usize,
+    start_column: usize,
 }
 
-impl<'a> Scanner<'a> {
-    pub fn new(lox: &'a mut Lox, source: String) -> Self {
+/// What scanning a single lexeme produces, None for whitespace and comments
+type Scanned = Option<Result<Token, ScanError>>;
+
+#[derive(Clone, Debug)]
+pub struct ScanError {
+    pub span: Span,
+    pub message: String,
+    // The source ended before the token did, so more input could complete it
+    pub unterminated: bool,
+}
+
+impl Scanner {
+    pub fn new(source: String) -> Self {
         Scanner {
-            lox,
             source,
+            done: false,
 
-            tokens: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+        }
+    }
+
+    /// Numbers lines from `line` instead of 1, for source that continues earlier input
+    pub fn with_first_line(source: String, line: usize) -> Self {
+        Scanner {
+            line,
+            start_line: line,
+            ..Self::new(source)
         }
     }
 
     fn advance(&mut self) -> Option<char> {
         let c = self.peek();
-        if c.is_some() {
-            self.current += 1;
+        if let Some(c) = c {
+            self.current += c.len_utf8();
+            self.column += 1;
         }
         c
     }
 
+    /// Must be called after consuming each '\n'
+    fn newline(&mut self) {
+        self.line += 1;
+        self.column = 1;
+    }
+
     fn peek(&self) -> Option<char> {
         self.source[self.current..].chars().next()
     }
 
     fn match_next_char(&mut self, expected: char) -> bool {
         if self.peek().is_some_and(|c| c == expected) {
-            self.current += 1;
+            self.advance();
             true
         } else {
             false
@@ -52,58 +91,161 @@ impl<'a> Scanner<'a> {
     }
 
     fn peek_next(&self) -> Option<char> {
-        self.source[self.current + 1..].chars().next()
+        self.source[self.current..].chars().nth(1)
+    }
+
+    /// An error at the start of the current token
+    fn error(&self, message: &str) -> Scanned {
+        self.scan_error(message, false)
+    }
+
+    fn unterminated_error(&self, message: &str) -> Scanned {
+        self.scan_error(message, true)
+    }
+
+    fn scan_error(&self, message: &str, unterminated: bool) -> Scanned {
+        Some(Err(ScanError {
+            span: Span {
+                line: self.start_line,
+                column: self.start_column,
+                len: 1,
+                start: self.start,
+                byte_len: self.source[self.start..]
+                    .chars()
+                    .next()
+                    .map_or(0, char::len_utf8),
+            },
+            message: message.to_string(),
+            unterminated,
+        }))
     }
 
-    fn add_token(&mut self, token_type: TokenType) {
+    fn add_token(&self, token_type: TokenType) -> Scanned {
         let lexeme = self.source[self.start..self.current].to_string();
-        self.tokens.push(Token::new(token_type, lexeme, self.line))
+        Some(Ok(Token::new(
+            token_type,
+            lexeme,
+            self.start_line,
+            self.start_column,
+            self.start,
+        )))
     }
 
-    fn string(&mut self) {
+    fn string(&mut self) -> Scanned {
         loop {
             match self.advance() {
                 Some('"') => {
-                    self.add_token(TokenType::String(
+                    return self.add_token(TokenType::String(
                         self.source[self.start + 1..self.current - 1].to_string(),
                     ));
-                    return;
                 }
-                Some('\n') => self.line += 1,
+                Some('\n') => self.newline(),
                 Some(_) => (),
-                None => {
-                    self.lox.error(self.line, "Unterminated string.");
-                    return;
+                None => return self.unterminated_error("Unterminated string."),
+            }
+        }
+    }
+
+    /// Skips a `/* ... */` comment whose opening `/*` was already consumed
+    /// Comments can nest, so each `/*` needs its own matching `*/`
+    fn block_comment(&mut self) -> Scanned {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some('/') if self.match_next_char('*') => depth += 1,
+                Some('*') if self.match_next_char('/') => depth -= 1,
+                Some('\n') => self.newline(),
+                Some(_) => (),
+                None => return self.unterminated_error("Unterminated block comment."),
+            }
+        }
+        None
+    }
+
+    /// Consumes the rest of a run of digits, which can be grouped by single underscores
+    /// as in `1_000`
+    /// Returns false for an underscore not followed by a digit, e.g. in `1__0` or `1_.0`
+    fn digits(&mut self) -> bool {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => {
+                    self.advance();
                 }
+                Some('_') => {
+                    self.advance();
+                    if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        return false;
+                    }
+                }
+                _ => return true,
             }
         }
     }
 
-    fn number(&mut self) {
-        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+    /// Reports a misplaced underscore, skipping the rest of its run of digits and underscores
+    /// so that e.g. `1__0` is one error rather than another for `_0`
+    fn misplaced_separator(&mut self) -> Scanned {
+        while self.peek().is_some_and(|c| c == '_' || c.is_ascii_digit()) {
             self.advance();
         }
+        self.error("Misplaced '_' in number.")
+    }
+
+    fn number(&mut self) -> Scanned {
+        if !self.digits() {
+            return self.misplaced_separator();
+        }
         if self.peek().is_some_and(|c| c == '.')
             && self.peek_next().is_some_and(|c| c.is_ascii_digit())
         {
             self.advance();
-            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            if !self.digits() {
+                return self.misplaced_separator();
+            }
+        }
+        // The exponent must directly follow a digit, so `1.e5` does not scan as one number
+        if self.peek().is_some_and(|c| c == 'e' || c == 'E') {
+            self.advance();
+            if self.peek().is_some_and(|c| c == '+' || c == '-') {
                 self.advance();
             }
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => (),
+                Some('_') => return self.misplaced_separator(),
+                _ => return self.error("Expect digits in exponent."),
+            }
+            if !self.digits() {
+                return self.misplaced_separator();
+            }
         }
         let literal = self.source[self.start..self.current]
+            .replace('_', "")
             .parse()
             .expect("Lexeme was checked, should be valid float");
-        self.add_token(TokenType::Number(literal));
+        self.add_token(TokenType::Number(literal))
     }
 
-    fn identifier(&mut self) {
-        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+    /// Identifiers can be written in any script, e.g. `café` or `名前`, following Unicode's
+    /// XID_Start and XID_Continue, so combining marks as in a decomposed `café` are kept
+    fn identifier(&mut self) -> Scanned {
+        while self.peek().is_some_and(|c| c.is_xid_continue()) {
             self.advance();
         }
-        let token_type = match &self.source[self.start..self.current] {
+        let lexeme = &self.source[self.start..self.current];
+        // A word of only underscores and digits, like `_1` or `_1_000`, is a number
+        // with a leading '_' rather than an identifier
+        if lexeme.contains(|c: char| c.is_ascii_digit())
+            && lexeme.chars().all(|c| c == '_' || c.is_ascii_digit())
+        {
+            return self.misplaced_separator();
+        }
+        let token_type = match lexeme {
             "and" => TokenType::And,
+            "break" => TokenType::Break,
+            "case" => TokenType::Case,
             "class" => TokenType::Class,
+            "continue" => TokenType::Continue,
+            "default" => TokenType::Default,
             "else" => TokenType::Else,
             "false" => TokenType::False,
             "fun" => TokenType::Fun,
@@ -114,65 +256,114 @@ impl<'a> Scanner<'a> {
             "print" => TokenType::Print,
             "return" => TokenType::Return,
             "super" => TokenType::Super,
+            "switch" => TokenType::Switch,
             "this" => TokenType::This,
             "true" => TokenType::True,
             "var" => TokenType::Var,
             "while" => TokenType::While,
             _ => TokenType::Identifier,
         };
-        self.add_token(token_type);
+        self.add_token(token_type)
     }
 
-    fn scan_token(&mut self) -> Result<(), &str> {
-        let Some(c) = self.advance() else {
-            return Err("End of source");
-        };
-
+    /// Scans the lexeme starting with `c`, which was just consumed
+    fn scan_token(&mut self, c: char) -> Scanned {
         match c {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
-            '-' => self.add_token(TokenType::Minus),
-            '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::Semicolon),
-            '*' => self.add_token(TokenType::Star),
+            '%' => self.add_token(TokenType::Percent),
+            '?' => self.add_token(TokenType::Question),
+            ':' => self.add_token(TokenType::Colon),
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
+            '~' => self.add_token(TokenType::Tilde),
 
             '!' if self.match_next_char('=') => self.add_token(TokenType::BangEqual),
             '!' => self.add_token(TokenType::Bang),
             '=' if self.match_next_char('=') => self.add_token(TokenType::EqualEqual),
             '=' => self.add_token(TokenType::Equal),
             '>' if self.match_next_char('=') => self.add_token(TokenType::GreaterEqual),
+            '>' if self.match_next_char('>') => self.add_token(TokenType::GreaterGreater),
             '>' => self.add_token(TokenType::Greater),
             '<' if self.match_next_char('=') => self.add_token(TokenType::LessEqual),
+            '<' if self.match_next_char('<') => self.add_token(TokenType::LessLess),
             '<' => self.add_token(TokenType::Less),
+            '-' if self.match_next_char('=') => self.add_token(TokenType::MinusEqual),
+            '-' if self.match_next_char('-') => self.add_token(TokenType::MinusMinus),
+            '-' => self.add_token(TokenType::Minus),
+            '+' if self.match_next_char('=') => self.add_token(TokenType::PlusEqual),
+            '+' if self.match_next_char('+') => self.add_token(TokenType::PlusPlus),
+            '+' => self.add_token(TokenType::Plus),
+            '*' if self.match_next_char('=') => self.add_token(TokenType::StarEqual),
+            '*' => self.add_token(TokenType::Star),
             '/' if self.match_next_char('/') => {
                 while self.peek().is_some_and(|c| c != '\n') {
                     self.advance();
                 }
+                None
             }
+            '/' if self.match_next_char('*') => self.block_comment(),
+            '/' if self.match_next_char('=') => self.add_token(TokenType::SlashEqual),
             '/' => self.add_token(TokenType::Slash),
 
-            ' ' | '\r' | '\t' => (),
-            '\n' => self.line += 1,
+            ' ' | '\r' | '\t' => None,
+            '\n' => {
+                self.newline();
+                None
+            }
             '"' => self.string(),
 
             _ if c.is_ascii_digit() => self.number(),
-            _ if c.is_ascii_alphabetic() => self.identifier(),
-            _ => self.lox.error(self.line, "Unexpected character."),
-        };
-        Ok(())
+            _ if c.is_xid_start() || c == '_' => self.identifier(),
+            _ => self.error("Unexpected character."),
+        }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
-        while self.scan_token().is_ok() {
-            self.start = self.current;
+    /// Scans the whole source, collecting every error instead of stopping at the first
+    pub fn scan_tokens(self) -> Result<Vec<Token>, Vec<ScanError>> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for scanned in self {
+            match scanned {
+                Ok(token) => tokens.push(token),
+                Err(err) => errors.push(err),
+            }
+        }
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
         }
+    }
+}
+
+impl Iterator for Scanner {
+    type Item = Result<Token, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, String::new(), self.line));
-        self.tokens.clone()
+            let Some(c) = self.advance() else {
+                if self.done {
+                    return None;
+                }
+                self.done = true;
+                return self.add_token(TokenType::Eof);
+            };
+            if let Some(scanned) = self.scan_token(c) {
+                return Some(scanned);
+            }
+        }
     }
 }
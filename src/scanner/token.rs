@@ -1,19 +1,32 @@
+use crate::diagnostic::Span;
 use crate::scanner::token_type::TokenType;
 use std::fmt;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Token {
     token_type: TokenType,
     lexeme: String,
     line: usize,
+    // 1-based, counted in characters from the start of the line
+    column: usize,
+    // Byte offset of the lexeme in the source it was scanned from
+    start: usize,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: usize) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        line: usize,
+        column: usize,
+        start: usize,
+    ) -> Self {
         Token {
             token_type,
             lexeme,
             line,
+            column,
+            start,
         }
     }
 
@@ -25,6 +38,22 @@ impl Token {
         self.line
     }
 
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Where the lexeme is in the source it was scanned from, by line and column
+    /// as well as by byte offset
+    pub fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            len: self.lexeme.chars().count(),
+            start: self.start,
+            byte_len: self.lexeme.len(),
+        }
+    }
+
     pub fn lexeme(&self) -> String {
         self.lexeme.clone()
     }
@@ -7,13 +7,18 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
-    Minus,
-    Plus,
     Semicolon,
-    Slash,
-    Star,
+    Percent,
+    Question,
+    Colon,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     // One or two character tokens.
     Bang,
@@ -22,8 +27,20 @@ pub enum TokenType {
     EqualEqual,
     Greater,
     GreaterEqual,
+    GreaterGreater,
     Less,
     LessEqual,
+    LessLess,
+    Minus,
+    MinusEqual,
+    MinusMinus,
+    Plus,
+    PlusEqual,
+    PlusPlus,
+    Slash,
+    SlashEqual,
+    Star,
+    StarEqual,
 
     // Literals.
     Identifier,
@@ -32,7 +49,11 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
+    Case,
     Class,
+    Continue,
+    Default,
     Else,
     False,
     Fun,
@@ -43,6 +64,7 @@ pub enum TokenType {
     Print,
     Return,
     Super,
+    Switch,
     This,
     True,
     Var,
@@ -59,6 +81,8 @@ impl fmt::Display for TokenType {
             Self::RightParen => f.write_str("RightParen"),
             Self::LeftBrace => f.write_str("LeftBrace"),
             Self::RightBrace => f.write_str("RightBrace"),
+            Self::LeftBracket => f.write_str("LeftBracket"),
+            Self::RightBracket => f.write_str("RightBracket"),
             Self::Comma => f.write_str("Comma"),
             Self::Dot => f.write_str("Dot"),
             Self::Minus => f.write_str("Minus"),
@@ -66,19 +90,38 @@ impl fmt::Display for TokenType {
             Self::Semicolon => f.write_str("Semicolon"),
             Self::Slash => f.write_str("Slash"),
             Self::Star => f.write_str("Star"),
+            Self::Percent => f.write_str("Percent"),
+            Self::Question => f.write_str("Question"),
+            Self::Colon => f.write_str("Colon"),
+            Self::Ampersand => f.write_str("Ampersand"),
+            Self::Pipe => f.write_str("Pipe"),
+            Self::Caret => f.write_str("Caret"),
+            Self::Tilde => f.write_str("Tilde"),
             Self::Bang => f.write_str("Bang"),
             Self::BangEqual => f.write_str("BangEqual"),
             Self::Equal => f.write_str("Equal"),
             Self::EqualEqual => f.write_str("EqualEqual"),
             Self::Greater => f.write_str("Greater"),
             Self::GreaterEqual => f.write_str("GreaterEqual"),
+            Self::GreaterGreater => f.write_str("GreaterGreater"),
             Self::Less => f.write_str("Less"),
             Self::LessEqual => f.write_str("LessEqual"),
+            Self::LessLess => f.write_str("LessLess"),
+            Self::MinusEqual => f.write_str("MinusEqual"),
+            Self::MinusMinus => f.write_str("MinusMinus"),
+            Self::PlusEqual => f.write_str("PlusEqual"),
+            Self::PlusPlus => f.write_str("PlusPlus"),
+            Self::SlashEqual => f.write_str("SlashEqual"),
+            Self::StarEqual => f.write_str("StarEqual"),
             Self::Identifier => f.write_str("Identifier"),
             Self::String(s) => write!(f, "String({s})"),
             Self::Number(n) => write!(f, "Number({n})"),
             Self::And => f.write_str("And"),
+            Self::Break => f.write_str("Break"),
+            Self::Case => f.write_str("Case"),
             Self::Class => f.write_str("Class"),
+            Self::Continue => f.write_str("Continue"),
+            Self::Default => f.write_str("Default"),
             Self::Else => f.write_str("Else"),
             Self::False => f.write_str("False"),
             Self::Fun => f.write_str("Fun"),
@@ -89,6 +132,7 @@ impl fmt::Display for TokenType {
             Self::Print => f.write_str("Print"),
             Self::Return => f.write_str("Return"),
             Self::Super => f.write_str("Super"),
+            Self::Switch => f.write_str("Switch"),
             Self::This => f.write_str("This"),
             Self::True => f.write_str("True"),
             Self::Var => f.write_str("Var"),
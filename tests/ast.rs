@@ -0,0 +1,213 @@
+mod common;
+
+use common::{ast, output};
+use rlox::ast::{program_to_json, Expr, Literal, Stmt};
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+
+#[test]
+fn operators_and_nested_groupings() {
+    assert_eq!(ast("1 + 2 * 3;"), "(expr (+ 1 (* 2 3)))");
+    assert_eq!(ast("-((1 + 2));"), "(expr (- (group (group (+ 1 2)))))");
+    assert_eq!(
+        ast("!true and nil or false;"),
+        "(expr (or (and (! true) nil) false))"
+    );
+    assert_eq!(ast("x ? 1 : 2;"), "(expr (? x 1 2))");
+}
+
+#[test]
+fn variables_and_assignments() {
+    assert_eq!(ast("var x;"), "(var x)");
+    assert_eq!(ast("var y = \"s\";"), "(var y \"s\")");
+    assert_eq!(ast("x = y;"), "(expr (= x y))");
+    assert_eq!(ast("x++; --x;"), "(expr (post++ x))\n(expr (pre-- x))");
+}
+
+#[test]
+fn calls_properties_and_collections() {
+    assert_eq!(ast("f(1, 2)(3);"), "(expr (call (call f 1 2) 3))");
+    assert_eq!(ast("a.b; a.b = 3;"), "(expr (get a b))\n(expr (set a b 3))");
+    assert_eq!(ast("[1, 2][0];"), "(expr (index (list 1 2) 0))");
+    assert_eq!(ast("l[0] = 1;"), "(expr (set-index l 0 1))");
+    assert_eq!(ast("print {\"k\": 1};"), "(print (map (\"k\" 1)))");
+}
+
+#[test]
+fn if_else_indents_its_branches() {
+    assert_eq!(
+        ast("if (x) print 1; else { print 2; print 3; }"),
+        "(if (group x)\n  (print 1)\n  (block\n    (print 2)\n    (print 3)))"
+    );
+    assert_eq!(ast("if x print 1;"), "(if x\n  (print 1))");
+}
+
+#[test]
+fn loops_and_switch() {
+    assert_eq!(
+        ast("while (x) { print 1; continue; }"),
+        "(while x\n  (block\n    (print 1)\n    (continue)))"
+    );
+    assert_eq!(ast("while (x) break;"), "(while x\n  (break))");
+    assert_eq!(
+        ast("switch (x) { case 1: print 1; default: print 2; }"),
+        "(switch x\n  (case 1\n    (print 1))\n  (default\n    (print 2)))"
+    );
+}
+
+#[test]
+fn functions_and_lambdas() {
+    assert_eq!(ast("fun g(a, b) { return; }"), "(fun g (a b)\n  (return))");
+    assert_eq!(
+        ast("fun (a) { return a; };"),
+        "(expr (fun (a)\n  (return a)))"
+    );
+}
+
+#[test]
+fn classes_with_every_kind_of_method() {
+    assert_eq!(
+        ast("class A < B { init(a) { this.a = a; super.init(); } class s() {} g { return 1; } }"),
+        "(class A (< B)\n  \
+           (static (fun s ()))\n  \
+           (fun init (a)\n    \
+             (expr (set this a a))\n    \
+             (expr (call (super init))))\n  \
+           (getter g\n    \
+             (return 1)))"
+    );
+}
+
+/// The value of the literal that makes up the whole expression statement in `source`
+fn literal(source: &str) -> Literal {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+    let program = Parser::new(tokens).parse().unwrap();
+    match &program[..] {
+        [Stmt::Expr {
+            expr: Expr::Literal { value },
+        }] => value.clone(),
+        _ => panic!("{source:?} should be a single literal"),
+    }
+}
+
+#[test]
+fn literals_are_parsed_into_values() {
+    assert_eq!(literal("12.5;"), Literal::Number(12.5));
+    assert_eq!(literal("\"hi\";"), Literal::String("hi".into()));
+    assert_eq!(literal("true;"), Literal::Bool(true));
+    assert_eq!(literal("false;"), Literal::Bool(false));
+    assert_eq!(literal("nil;"), Literal::Nil);
+}
+
+#[test]
+fn each_kind_of_literal_evaluates_to_its_value() {
+    assert_eq!(
+        output("print 12.5; print \"hi\"; print true; print false; print nil;"),
+        "12.5\nhi\ntrue\nfalse\nnil\n"
+    );
+}
+
+fn json(source: &str) -> String {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+    program_to_json(&Parser::new(tokens).parse().unwrap())
+}
+
+#[test]
+fn json_of_a_var_declaration() {
+    assert_eq!(
+        json("var x = 1 + 2;"),
+        r#"[{"type":"VarDecl","var_name":"x","initializer":{"type":"Binary","operator":"+","#
+            .to_string()
+            + r#""left":{"type":"Literal","value":1},"right":{"type":"Literal","value":2}}}]"#
+    );
+    assert_eq!(
+        json("var y;"),
+        r#"[{"type":"VarDecl","var_name":"y","initializer":null}]"#
+    );
+}
+
+#[test]
+fn json_of_an_if_without_else() {
+    assert_eq!(
+        json("if x print true;"),
+        r#"[{"type":"If","condition":{"type":"Variable","name":"x"},"#.to_string()
+            + r#""then_stmt":{"type":"Print","expr":{"type":"Literal","value":true}},"#
+            + r#""else_stmt":null}]"#
+    );
+}
+
+#[test]
+fn json_of_functions_and_lambdas() {
+    assert_eq!(
+        json("fun f(a) { return a; }"),
+        r#"[{"type":"Function","name":"f","params":["a"],"#.to_string()
+            + r#""body":[{"type":"Return","value":{"type":"Variable","name":"a"}}],"#
+            + r#""is_getter":false}]"#
+    );
+    // A lambda has no name
+    assert_eq!(
+        json("fun () {};"),
+        r#"[{"type":"Expr","expr":{"type":"Lambda","params":[],"body":[],"is_getter":false}}]"#
+    );
+}
+
+#[test]
+fn json_of_a_class() {
+    assert_eq!(
+        json("class A < B { class s() {} g { return this; } }"),
+        r#"[{"type":"Class","name":"A","superclass":{"type":"Variable","name":"B"},"#.to_string()
+            + r#""methods":[{"type":"Function","name":"g","params":[],"#
+            + r#""body":[{"type":"Return","value":{"type":"This"}}],"is_getter":true}],"#
+            + r#""static_methods":[{"type":"Function","name":"s","params":[],"body":[],"#
+            + r#""is_getter":false}]}]"#
+    );
+}
+
+#[test]
+fn json_escapes_strings() {
+    // Lox has no escape sequences, so a backslash or a raw newline is kept as written
+    assert_eq!(
+        json("print \"a\\b\n\tc\";"),
+        r#"[{"type":"Print","expr":{"type":"Literal","value":"a\\b\n\tc"}}]"#
+    );
+}
+
+#[test]
+fn json_of_nil_and_huge_numbers() {
+    let huge = "9".repeat(400);
+    assert_eq!(
+        json(&format!("[nil, {huge}];")),
+        r#"[{"type":"Expr","expr":{"type":"ListLiteral","elements":"#.to_string()
+            + r#"[{"type":"Literal","value":null},{"type":"Literal","value":null}]}}]"#
+    );
+}
+
+#[test]
+fn json_of_a_single_statement_and_expression() {
+    let tokens = Scanner::new("a.b = [1];".to_string())
+        .scan_tokens()
+        .unwrap();
+    let program = Parser::new(tokens).parse().unwrap();
+    let Stmt::Expr { expr } = &program[0] else {
+        panic!("should be an expression statement");
+    };
+    assert_eq!(
+        expr.to_json(),
+        r#"{"type":"Set","object":{"type":"Variable","name":"a"},"name":"b","#.to_string()
+            + r#""value":{"type":"ListLiteral","elements":[{"type":"Literal","value":1}]}}"#
+    );
+    assert_eq!(
+        program[0].to_json(),
+        format!(r#"{{"type":"Expr","expr":{}}}"#, expr.to_json())
+    );
+}
+
+#[test]
+fn json_of_a_switch() {
+    assert_eq!(
+        json("switch (x) { case 1: print 1; }"),
+        r#"[{"type":"Switch","discriminant":{"type":"Variable","name":"x"},"cases":"#.to_string()
+            + r#"[{"value":{"type":"Literal","value":1},"#
+            + r#""stmts":[{"type":"Print","expr":{"type":"Literal","value":1}}]}],"default":null}]"#
+    );
+}
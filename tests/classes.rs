@@ -0,0 +1,269 @@
+mod common;
+
+use common::{compile_errors, output, runtime_error};
+use rlox::RuntimeError;
+
+#[test]
+fn fields_can_be_set_and_read() {
+    assert_eq!(
+        output("class Box {} var b = Box(); b.value = 42; print b.value;"),
+        "42\n"
+    );
+}
+
+#[test]
+fn methods_can_be_called_on_an_instance() {
+    assert_eq!(
+        output("class Greeter { greet() { print \"hi\"; } } Greeter().greet();"),
+        "hi\n"
+    );
+}
+
+#[test]
+fn classes_and_instances_print_their_names() {
+    assert_eq!(
+        output("class Box {} print Box; print Box();"),
+        "Box\nBox instance\n"
+    );
+}
+
+#[test]
+fn reading_an_undefined_property() {
+    let err = runtime_error("class Box {} Box().missing;");
+    assert!(matches!(err, RuntimeError::UndefinedProperty(_)));
+    assert_eq!(err.token().lexeme(), "missing");
+}
+
+#[test]
+fn methods_see_their_instance_as_this() {
+    assert_eq!(
+        output("class P { getX() { return this.x; } } var p = P(); p.x = 3; print p.getX();"),
+        "3\n"
+    );
+}
+
+#[test]
+fn a_method_stored_in_a_variable_stays_bound_to_its_instance() {
+    assert_eq!(
+        output(
+            "class P { getX() { return this.x; } }\n\
+             var p = P();\n\
+             var getX = p.getX;\n\
+             p.x = \"bound\";\n\
+             print getX();"
+        ),
+        "bound\n"
+    );
+}
+
+#[test]
+fn init_runs_when_the_class_is_called() {
+    assert_eq!(
+        output(
+            "class Point { init(x, y) { this.x = x; this.y = y; } }\n\
+             var p = Point(1, 2);\n\
+             print p.x + p.y;"
+        ),
+        "3\n"
+    );
+}
+
+#[test]
+fn calling_a_class_checks_the_arity_of_init() {
+    let err = runtime_error("class Point { init(x, y) {} } Point(1);");
+    assert!(matches!(err, RuntimeError::ArityMismatch(_, 2, 1)));
+    let err = runtime_error("class Empty {} Empty(1);");
+    assert!(matches!(err, RuntimeError::ArityMismatch(_, 0, 1)));
+}
+
+#[test]
+fn init_can_return_early_without_a_value() {
+    assert_eq!(
+        output(
+            "class A { init(early) { this.v = 1; if (early) return; this.v = 2; } }\n\
+             print A(true).v; print A(false).v;"
+        ),
+        "1\n2\n"
+    );
+}
+
+#[test]
+fn init_cant_return_a_value() {
+    assert_eq!(
+        compile_errors("class A { init() { return 1; } }"),
+        ["Can't return a value from an initializer"]
+    );
+}
+
+#[test]
+fn subclasses_inherit_methods() {
+    assert_eq!(
+        output("class A { hi() { print \"A\"; } } class B < A {} B().hi();"),
+        "A\n"
+    );
+}
+
+#[test]
+fn subclasses_can_override_methods() {
+    assert_eq!(
+        output("class A { hi() { print \"A\"; } } class B < A { hi() { print \"B\"; } } B().hi();"),
+        "B\n"
+    );
+}
+
+#[test]
+fn super_calls_the_parent_method_on_the_same_instance() {
+    assert_eq!(
+        output(
+            "class A { name() { return \"A of \" + this.id; } }\n\
+             class B < A { name() { return \"B, \" + super.name(); } }\n\
+             var b = B(); b.id = \"b\";\n\
+             print b.name();"
+        ),
+        "B, A of b\n"
+    );
+}
+
+#[test]
+fn superclass_must_be_a_class() {
+    let err = runtime_error("var NotAClass = 1; class B < NotAClass {}");
+    assert!(matches!(err, RuntimeError::SuperclassMustBeClass(_)));
+}
+
+#[test]
+fn an_instance_without_to_string_prints_as_its_class_name() {
+    assert_eq!(output("class Foo {} print Foo();"), "Foo instance\n");
+}
+
+#[test]
+fn print_uses_a_to_string_method() {
+    assert_eq!(
+        output(
+            "class P {\n\
+               init(x) { this.x = x; }\n\
+               toString() { return \"P(\" + str(this.x) + \")\"; }\n\
+             }\n\
+             print P(1);"
+        ),
+        "P(1)\n"
+    );
+}
+
+#[test]
+fn to_string_is_inherited() {
+    assert_eq!(
+        output("class A { toString() { return \"an A\"; } } class B < A {} print B();"),
+        "an A\n"
+    );
+}
+
+#[test]
+fn to_string_taking_arguments_is_not_used() {
+    assert_eq!(
+        output("class A { toString(x) { return \"no\"; } } print A();"),
+        "A instance\n"
+    );
+}
+
+#[test]
+fn instances_inside_a_list_print_without_to_string() {
+    assert_eq!(
+        output("class A { toString() { return \"an A\"; } } print [A()];"),
+        "[A instance]\n"
+    );
+}
+
+#[test]
+fn an_error_in_to_string_stops_the_print() {
+    let err = runtime_error("class A { toString() { return nil + 1; } }\nprint A();");
+    assert!(matches!(err, RuntimeError::InvalidBinaryOperand(..)));
+    assert_eq!(err.token().line(), 1);
+}
+
+#[test]
+fn a_getter_runs_when_its_property_is_read() {
+    assert_eq!(
+        output(
+            "class Circle {\n\
+               init(r) { this.r = r; }\n\
+               area { return 3 * this.r * this.r; }\n\
+             }\n\
+             var c = Circle(2); print c.area; c.r = 1; print c.area;"
+        ),
+        "12\n3\n"
+    );
+}
+
+#[test]
+fn a_method_read_without_parens_is_still_a_callable() {
+    assert_eq!(
+        output("class A { m() { return 1; } } var m = A().m; print m; print m();"),
+        "<fn m>\n1\n"
+    );
+}
+
+#[test]
+fn a_getters_value_is_not_callable_unless_it_returns_a_function() {
+    assert!(matches!(
+        runtime_error("class A { g { return 1; } } A().g();"),
+        RuntimeError::NotCallable(_)
+    ));
+    assert_eq!(
+        output("class A { g { return fun () { return 2; }; } } print A().g();"),
+        "2\n"
+    );
+}
+
+#[test]
+fn getters_are_inherited() {
+    assert_eq!(
+        output("class A { name { return \"A\"; } } class B < A {} print B().name;"),
+        "A\n"
+    );
+}
+
+#[test]
+fn static_methods_are_called_on_the_class() {
+    assert_eq!(
+        output(
+            "class Math { class square(n) { return n * n; } }\n\
+             print Math.square(4); print Math.square;"
+        ),
+        "16\n<fn square>\n"
+    );
+}
+
+#[test]
+fn static_methods_are_not_visible_on_instances() {
+    let err = runtime_error("class Math { class square(n) { return n * n; } }\nMath().square(2);");
+    assert!(matches!(err, RuntimeError::UndefinedProperty(_)));
+    assert_eq!(err.token().lexeme(), "square");
+}
+
+#[test]
+fn instance_methods_are_not_visible_on_the_class() {
+    assert!(matches!(
+        runtime_error("class A { m() {} } A.m();"),
+        RuntimeError::UndefinedProperty(_)
+    ));
+}
+
+#[test]
+fn static_methods_can_construct_instances_and_are_inherited() {
+    assert_eq!(
+        output(
+            "class A { class make() { return A(); } }\n\
+             class B < A {}\n\
+             print A.make(); print B.make();"
+        ),
+        "A instance\nA instance\n"
+    );
+}
+
+#[test]
+fn static_methods_cant_use_this() {
+    assert_eq!(
+        compile_errors("class A { class s() { return this; } }"),
+        ["Can't use 'this' outside of a class"]
+    );
+}
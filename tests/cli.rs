@@ -0,0 +1,298 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Output, Stdio};
+
+/// Writes `source` to a script file named after the test, so parallel tests don't collide
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rlox-{}-{name}.lox", process::id()));
+    fs::write(&path, source).expect("script should be written");
+    path
+}
+
+fn rlox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .output()
+        .expect("rlox should start")
+}
+
+fn run_script(name: &str, source: &str) -> Output {
+    run_script_with(name, &[], source)
+}
+
+/// Runs a script with `flags` placed before its filename
+fn run_script_with(name: &str, flags: &[&str], source: &str) -> Output {
+    let path = script(name, source);
+    let mut args = flags.to_vec();
+    args.push(path.to_str().unwrap());
+    let output = rlox(&args);
+    fs::remove_file(path).expect("script should be removed");
+    output
+}
+
+/// Runs the REPL with `input` typed in, one line at a time
+fn repl(input: &str) -> Output {
+    rlox_with_input(&[], input)
+}
+
+/// Runs rlox with `input` as its stdin
+fn rlox_with_input(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("rlox should start");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .expect("input should be written");
+    child.wait_with_output().expect("rlox should finish")
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn a_script_that_runs_exits_with_0() {
+    let output = run_script("ok", "print 1 + 1;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"2\n");
+}
+
+#[test]
+fn a_syntax_error_exits_with_65() {
+    let output = run_script("syntax-error", "print (1;");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stderr(&output), "[line 1:9] Error at ';': Expect ')'\n");
+}
+
+#[test]
+fn a_runtime_error_exits_with_70() {
+    let output = run_script("runtime-error", "print \"before\";\nprint nil + 1;");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(output.stdout, b"before\n");
+    assert!(stderr(&output).starts_with("[line 2:11] Operands must be"));
+}
+
+#[test]
+fn dump_tokens_prints_one_token_per_line() {
+    let output = run_script_with("dump-tokens", &["--dump-tokens"], "var x = 1;\nprint x;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Var var 1\n\
+         Identifier x 1\n\
+         Equal = 1\n\
+         Number(1) 1 1\n\
+         Semicolon ; 1\n\
+         Print print 2\n\
+         Identifier x 2\n\
+         Semicolon ; 2\n\
+         Eof  2\n"
+    );
+}
+
+#[test]
+fn dump_tokens_does_not_run_the_script() {
+    let output = run_script_with("dump-tokens-no-run", &["--dump-tokens"], "print nil + 1;");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Operands"));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn dump_tokens_exits_with_65_on_a_lexical_error() {
+    let output = run_script_with("dump-tokens-error", &["--dump-tokens"], "print @;");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn the_binary_allows_deeper_recursion_than_the_library_default() {
+    let output = run_script(
+        "deep",
+        "fun g(n) { if (n > 0) return g(n - 1); return n; } print g(999);",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"0\n");
+
+    let output = run_script("too-deep", "fun f(n) { return f(n + 1); }\nf(0);");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(
+        stderr(&output).starts_with("[line 1:"),
+        "{}",
+        stderr(&output)
+    );
+    assert!(stderr(&output).contains("Stack overflow"));
+}
+
+#[test]
+fn dump_ast_prints_the_tree_without_running_it() {
+    let output = run_script_with("dump-ast", &["--dump-ast"], "print (1 + 2) * 3;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"(print (* (group (+ 1 2)) 3))\n");
+}
+
+#[test]
+fn dump_ast_exits_with_65_on_a_syntax_error() {
+    let output = run_script_with("dump-ast-error", &["--dump-ast"], "print (1;");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+    assert_eq!(stderr(&output), "[line 1:9] Error at ';': Expect ')'\n");
+}
+
+#[test]
+fn dump_json_prints_the_program_as_one_line() {
+    let output = run_script_with("dump-json", &["--dump-json"], "print nil;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "[{\"type\":\"Print\",\"expr\":{\"type\":\"Literal\",\"value\":null}}]\n"
+    );
+}
+
+#[test]
+fn dump_json_exits_with_65_on_a_syntax_error() {
+    let output = run_script_with("dump-json-error", &["--dump-json"], "print (1;");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn an_unterminated_string_names_the_line_it_opens_on() {
+    let output = run_script("unterminated", "print 1;\nprint \"open\n\n\n");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stderr(&output), "[line 2:7] Error: Unterminated string.\n");
+}
+
+#[test]
+fn repl_errors_name_the_line_counted_from_the_start_of_the_session() {
+    let output = repl("var a = 1;\nvar b = 2;\nprint c;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stderr(&output).starts_with("[line 3:7] Undefined variable"));
+}
+
+#[test]
+fn repl_counts_every_line_of_input_continued_over_several() {
+    let output = repl("{\nprint 1;\n}\nprint d;\n");
+    assert_eq!(output.stdout, b"> ... ... 1\n> > ");
+    assert!(stderr(&output).starts_with("[line 4:7] Undefined variable"));
+}
+
+#[test]
+fn repl_reset_clears_variables() {
+    let output = repl("var a = 1;\nprint a;\n:reset\nprint a;\n");
+    assert_eq!(output.stdout, b"> > 1\n> > > ");
+    assert!(stderr(&output).starts_with("[line 4:7] Undefined variable"));
+}
+
+#[test]
+fn repl_reports_an_unknown_meta_command() {
+    let output = repl(":oops\nprint 1;\n");
+    assert_eq!(output.stdout, b"> > 1\n> ");
+    assert_eq!(
+        stderr(&output),
+        "Unknown command ':oops', the only command is :reset\n"
+    );
+}
+
+#[test]
+fn eval_runs_code_from_the_command_line() {
+    let output = rlox(&["--eval", "print 1 + 2;"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"3\n");
+}
+
+#[test]
+fn eval_with_a_runtime_error_exits_with_70() {
+    let output = rlox(&["--eval", "print \"a\"; print nil + 2;"]);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(output.stdout, b"a\n");
+    assert!(stderr(&output).starts_with("[line 1:22] Operands must be"));
+}
+
+#[test]
+fn eval_with_a_syntax_error_exits_with_65() {
+    let output = rlox(&["--eval", "print (;"]);
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn eval_cannot_be_combined_with_a_script() {
+    for args in [&["--eval", "print 1;", "script.lox"][..], &["--eval"]] {
+        let output = rlox(args);
+        assert_eq!(output.status.code(), Some(64));
+        assert_eq!(
+            output.stdout,
+            b"--eval takes the code to run, and can't be combined with a script\n"
+        );
+    }
+}
+
+#[test]
+fn a_script_named_dash_is_read_from_stdin() {
+    let output = rlox_with_input(&["-"], "var a = 20;\nprint a + 1;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"21\n");
+}
+
+#[test]
+fn a_script_from_stdin_exits_like_a_file() {
+    let output = rlox_with_input(&["-"], "print 1;\nprint nil + 1;");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(output.stdout, b"1\n");
+    let output = rlox_with_input(&["-"], "print (1;");
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn a_missing_script_exits_with_66() {
+    let path = std::env::temp_dir().join(format!("rlox-{}-missing.lox", process::id()));
+    let output = rlox(&[path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(66));
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "Error: could not open '{}': No such file or directory\n",
+            path.display()
+        )
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn an_unreadable_script_exits_with_66() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = script("unreadable", "print 1;");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o000)).unwrap();
+    // Root can read the file regardless, so there is nothing to check
+    if fs::read(&path).is_err() {
+        let output = rlox(&[path.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(66));
+        assert_eq!(
+            stderr(&output),
+            format!(
+                "Error: could not open '{}': Permission denied\n",
+                path.display()
+            )
+        );
+    }
+    fs::remove_file(path).expect("script should be removed");
+}
+
+#[test]
+fn a_directory_given_as_the_script_exits_with_66() {
+    let dir = std::env::temp_dir();
+    let output = rlox(&[dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(66));
+    assert!(stderr(&output).starts_with(&format!("Error: could not open '{}': ", dir.display())));
+}
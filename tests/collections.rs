@@ -0,0 +1,119 @@
+mod common;
+
+use common::{output, runtime_error};
+use rlox::RuntimeError;
+
+#[test]
+fn list_literals() {
+    assert_eq!(
+        output("print [1, \"two\", nil, [3]]; print []; print [1 + 1, 2 * 2];"),
+        "[1, two, nil, [3]]\n[]\n[2, 4]\n"
+    );
+}
+
+#[test]
+fn reading_and_writing_list_elements() {
+    assert_eq!(
+        output(
+            "var xs = [1, 2, 3]; print xs[0]; xs[1] = 20; print xs[1]; print xs[2] = 30; print xs;"
+        ),
+        "1\n20\n30\n[1, 20, 30]\n"
+    );
+}
+
+#[test]
+fn list_index_out_of_bounds() {
+    for source in ["print [1][1];", "var xs = []; xs[0] = 1;"] {
+        let err = runtime_error(source);
+        assert!(matches!(err, RuntimeError::IndexOutOfBounds(_)));
+        assert_eq!(err.token().lexeme(), "]");
+    }
+}
+
+#[test]
+fn list_index_must_be_a_non_negative_integer() {
+    for source in ["print [1][-1];", "print [1][0.5];", "print [1][\"0\"];"] {
+        assert!(matches!(
+            runtime_error(source),
+            RuntimeError::InvalidIndex(_)
+        ));
+    }
+}
+
+#[test]
+fn only_lists_and_maps_can_be_indexed() {
+    assert!(matches!(
+        runtime_error("print 1[0];"),
+        RuntimeError::NotIndexable(_)
+    ));
+}
+
+#[test]
+fn variables_sharing_a_list_see_each_others_changes() {
+    assert_eq!(
+        output(
+            "var a = [1, 2]; var b = a; b[0] = 10; print a;\n\
+             fun f(xs) { xs[1] = 20; } f(a); print b;"
+        ),
+        "[10, 2]\n[10, 20]\n"
+    );
+}
+
+#[test]
+fn a_list_containing_itself_prints_without_recursing_forever() {
+    assert_eq!(output("var xs = [1]; xs[0] = xs; print xs;"), "[[...]]\n");
+}
+
+#[test]
+fn map_literals_with_string_and_number_keys() {
+    assert_eq!(
+        output(
+            "var m = {\"a\": 1, 2: \"two\"}; print m[\"a\"]; print m[2];\n\
+             print {}; print {\"k\": [1]};"
+        ),
+        "1\ntwo\n{}\n{k: [1]}\n"
+    );
+}
+
+#[test]
+fn setting_map_entries() {
+    assert_eq!(
+        output("var m = {\"a\": 1}; m[\"a\"] = 10; print m[\"b\"] = 2; print m[\"a\"] + m[\"b\"];"),
+        "2\n12\n"
+    );
+}
+
+#[test]
+fn a_missing_key_reads_as_nil() {
+    assert_eq!(
+        output("var m = {\"a\": 1}; print m[\"b\"]; print m[1];"),
+        "nil\nnil\n"
+    );
+}
+
+#[test]
+fn map_keys_must_be_strings_or_numbers() {
+    for source in [
+        "var m = {}; m[nil] = 1;",
+        "print {}[[1]];",
+        "print {true: 1};",
+    ] {
+        assert!(matches!(
+            runtime_error(source),
+            RuntimeError::InvalidMapKey(_)
+        ));
+    }
+}
+
+#[test]
+fn variables_sharing_a_map_see_each_others_changes() {
+    assert_eq!(
+        output("var m = {\"a\": 1}; var n = m; n[\"a\"] = 9; print m[\"a\"];"),
+        "9\n"
+    );
+}
+
+#[test]
+fn a_map_containing_itself_prints_without_recursing_forever() {
+    assert_eq!(output("var m = {}; m[\"m\"] = m; print m;"), "{m: {...}}\n");
+}
@@ -0,0 +1,127 @@
+//! Helpers shared by the integration tests
+// Each test crate uses only some of them
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::thread;
+
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rlox::{Interpreter, LoxError, RuntimeError};
+
+/// Matches the thread main.rs runs the interpreter on
+const LARGE_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// A writer that can still be read after an interpreter takes ownership of it
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).expect("output should be UTF-8")
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An interpreter whose output goes to the returned buffer
+pub fn interpreter() -> (Interpreter, SharedBuffer) {
+    let out = SharedBuffer::default();
+    (Interpreter::with_writer(Box::new(out.clone())), out)
+}
+
+/// Runs `source` in a new interpreter, returning what it printed before finishing or failing
+pub fn run(source: &str) -> (String, Result<(), LoxError>) {
+    let (mut interpreter, out) = interpreter();
+    let result = rlox::run_source(&mut interpreter, source);
+    (out.contents(), result)
+}
+
+/// Runs `source`, which should succeed, and returns what it printed
+#[track_caller]
+pub fn output(source: &str) -> String {
+    let (out, result) = run(source);
+    if let Err(err) = result {
+        panic!("{source:?} should run, but failed with: {err}");
+    }
+    out
+}
+
+/// Runs `source`, which should fail at runtime, and returns the error
+#[track_caller]
+pub fn runtime_error(source: &str) -> RuntimeError {
+    match run(source).1 {
+        Err(LoxError::Runtime(err)) => err,
+        Err(err) => panic!("{source:?} should fail at runtime, but failed with: {err}"),
+        Ok(()) => panic!("{source:?} should fail at runtime, but ran"),
+    }
+}
+
+/// Runs `source`, which should fail to compile, and returns the message of each error
+#[track_caller]
+pub fn compile_errors(source: &str) -> Vec<String> {
+    match run(source).1 {
+        Err(LoxError::Compile(diagnostics)) => diagnostics
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == rlox::diagnostic::Severity::Error)
+            .map(|diagnostic| diagnostic.message)
+            .collect(),
+        Err(err) => panic!("{source:?} should fail to compile, but failed with: {err}"),
+        Ok(()) => panic!("{source:?} should fail to compile, but ran"),
+    }
+}
+
+/// Parses `source`, which should be valid, and prints each statement as an S-expression
+#[track_caller]
+pub fn ast(source: &str) -> String {
+    let tokens = Scanner::new(source.to_string())
+        .scan_tokens()
+        .expect("source should scan");
+    let program = Parser::new(tokens).parse().expect("source should parse");
+    program
+        .iter()
+        .map(|stmt| stmt.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Resolves `source`, which should be valid, and returns each warning with the line it is on
+#[track_caller]
+pub fn warnings(source: &str) -> Vec<(String, usize)> {
+    let tokens = Scanner::new(source.to_string())
+        .scan_tokens()
+        .expect("source should scan");
+    let program = Parser::new(tokens).parse().expect("source should parse");
+    let (mut interpreter, _) = interpreter();
+    let mut resolver = Resolver::new(&mut interpreter);
+    if resolver.resolve(&program).is_err() {
+        panic!("{source:?} should resolve");
+    }
+    resolver
+        .take_warnings()
+        .into_iter()
+        .map(|warning| (warning.message(), warning.token().line()))
+        .collect()
+}
+
+/// Runs `f` on a thread with as much stack as the rlox binary has,
+/// for tests that recurse as deep as the default call depth allows
+pub fn on_large_stack(f: impl FnOnce() + Send + 'static) {
+    thread::Builder::new()
+        .stack_size(LARGE_STACK_SIZE)
+        .spawn(f)
+        .expect("test thread should spawn")
+        .join()
+        .expect("test should pass");
+}
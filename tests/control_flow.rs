@@ -0,0 +1,310 @@
+mod common;
+
+use common::{compile_errors, output, runtime_error};
+use rlox::RuntimeError;
+
+#[test]
+fn while_with_an_empty_body() {
+    assert_eq!(
+        output("var i = 0; while ((i = i + 1) < 5) {} print i;"),
+        "5\n"
+    );
+}
+
+#[test]
+fn while_body_mutates_a_counter() {
+    assert_eq!(
+        output("var i = 0; while (i < 3) { print i; i = i + 1; }"),
+        "0\n1\n2\n"
+    );
+}
+
+#[test]
+fn while_body_never_runs_when_the_condition_starts_false() {
+    assert_eq!(
+        output("while (false) print \"body\"; print \"after\";"),
+        "after\n"
+    );
+}
+
+#[test]
+fn for_sums_one_to_five() {
+    assert_eq!(
+        output("var sum = 0; for (var i = 1; i <= 5; i = i + 1) sum = sum + i; print sum;"),
+        "15\n"
+    );
+}
+
+#[test]
+fn for_with_every_clause_empty_runs_until_break() {
+    assert_eq!(
+        output("var i = 0; for (;;) { i = i + 1; if (i == 3) break; } print i;"),
+        "3\n"
+    );
+}
+
+#[test]
+fn for_loop_variable_is_scoped_to_the_loop() {
+    assert_eq!(
+        output("var i = \"outer\"; for (var i = 0; i < 1; i = i + 1) {} print i;"),
+        "outer\n"
+    );
+}
+
+#[test]
+fn or_skips_an_undefined_right_operand_when_the_left_is_truthy() {
+    assert_eq!(output("print true or undefined;"), "true\n");
+    assert_eq!(output("print \"left\" or undefined;"), "left\n");
+}
+
+#[test]
+fn and_skips_an_undefined_right_operand_when_the_left_is_falsy() {
+    assert_eq!(output("print false and undefined;"), "false\n");
+    assert_eq!(output("print nil and undefined;"), "nil\n");
+}
+
+#[test]
+fn logical_operators_return_the_deciding_operand() {
+    assert_eq!(output("print nil or \"right\";"), "right\n");
+    assert_eq!(output("print 1 and 2;"), "2\n");
+}
+
+#[test]
+fn logical_operators_evaluate_the_right_operand_when_needed() {
+    let err = runtime_error("print false or undefined;");
+    assert!(matches!(err, RuntimeError::UndefinedVariable(_)));
+    let err = runtime_error("print true and undefined;");
+    assert!(matches!(err, RuntimeError::UndefinedVariable(_)));
+}
+
+#[test]
+fn break_exits_a_while() {
+    assert_eq!(
+        output("var i = 0; while (true) { if (i == 3) break; print i; i = i + 1; }"),
+        "0\n1\n2\n"
+    );
+}
+
+#[test]
+fn continue_skips_even_numbers() {
+    assert_eq!(
+        output("var i = 0; while (i < 6) { i = i + 1; if (i % 2 == 0) continue; print i; }"),
+        "1\n3\n5\n"
+    );
+}
+
+#[test]
+fn continue_in_a_for_still_runs_the_increment() {
+    assert_eq!(
+        output("for (var i = 0; i < 5; i = i + 1) { if (i == 2) continue; print i; }"),
+        "0\n1\n3\n4\n"
+    );
+}
+
+#[test]
+fn break_inside_a_nested_block_exits_the_loop() {
+    assert_eq!(
+        output("for (var i = 0; i < 5; i = i + 1) { { { if (i == 1) break; } } print i; }"),
+        "0\n"
+    );
+}
+
+#[test]
+fn break_only_exits_the_innermost_loop() {
+    assert_eq!(
+        output(
+            "for (var i = 0; i < 2; i = i + 1) \
+               for (var j = 0; j < 5; j = j + 1) { if (j == 1) break; print i; }"
+        ),
+        "0\n1\n"
+    );
+}
+
+#[test]
+fn break_and_continue_outside_a_loop() {
+    assert_eq!(compile_errors("break;"), ["'break' outside a loop"]);
+    assert_eq!(compile_errors("continue;"), ["'continue' outside a loop"]);
+    // A function body is not inside the loop it is declared in
+    assert_eq!(
+        compile_errors("while (true) { fun f() { break; } }"),
+        ["'break' outside a loop"]
+    );
+}
+
+#[test]
+fn dangling_else_runs_with_the_inner_if() {
+    assert_eq!(
+        output(
+            "if (true) if (false) print 1; else print 2;\n\
+             if (false) if (true) print 3; else print 4;"
+        ),
+        "2\n"
+    );
+}
+
+#[test]
+fn else_if_chain_takes_the_first_true_branch() {
+    let chain = "if (n < 0) print \"neg\"; else if (n == 0) print \"zero\"; else print \"pos\";";
+    let source: String = ["-1", "0", "5"]
+        .iter()
+        .map(|n| format!("{{ var n = {n}; {chain} }}\n"))
+        .collect();
+    assert_eq!(output(&source), "neg\nzero\npos\n");
+}
+
+#[test]
+fn for_loop_variable_is_undefined_after_the_loop() {
+    let err = runtime_error("for (var i = 0; i < 3; i = i + 1) {}\nprint i;");
+    assert!(matches!(err, RuntimeError::UndefinedVariable(_)));
+    assert_eq!(err.token().line(), 2);
+}
+
+#[test]
+fn closures_made_in_a_for_loop_share_its_variable() {
+    assert_eq!(
+        output(
+            "var fs = [];\n\
+             for (var i = 0; i < 3; i = i + 1) push(fs, fun () { return i; });\n\
+             print fs[0](); print fs[1](); print fs[2]();"
+        ),
+        "3\n3\n3\n"
+    );
+}
+
+#[test]
+fn and_returns_the_first_falsey_operand() {
+    assert_eq!(
+        output("print false and whatever; print nil and 1; print 1 and nil; print 0 and \"\";"),
+        "false\nnil\nnil\n\n"
+    );
+}
+
+#[test]
+fn or_returns_the_first_truthy_operand() {
+    assert_eq!(
+        output("print nil or \"x\"; print false or nil; print 0 or 1; print nil or false or 3;"),
+        "x\nnil\n0\n3\n"
+    );
+}
+
+#[test]
+fn short_circuited_operands_have_no_side_effects() {
+    assert_eq!(
+        output(
+            "var calls = 0;\n\
+             fun bump() { calls = calls + 1; return true; }\n\
+             false and bump(); true or bump(); print calls;\n\
+             true and bump(); false or bump(); print calls;"
+        ),
+        "0\n2\n"
+    );
+}
+
+#[test]
+fn continue_on_even_numbers_in_a_for_still_terminates() {
+    assert_eq!(
+        output("for (var i = 0; i < 10; i = i + 1) { if (i % 2 == 0) continue; print i; }"),
+        "1\n3\n5\n7\n9\n"
+    );
+}
+
+#[test]
+fn continue_in_a_nested_block_runs_the_increment() {
+    assert_eq!(
+        output(
+            "var total = 0;\n\
+             for (var i = 0; i < 4; i = i + 1) {\n\
+               { { if (i == 1) continue; } }\n\
+               total = total + i;\n\
+             }\n\
+             print total;"
+        ),
+        "5\n"
+    );
+}
+
+#[test]
+fn continue_in_an_inner_for_runs_only_the_inner_increment() {
+    assert_eq!(
+        output(
+            "for (var i = 0; i < 2; i = i + 1)\n\
+               for (var j = 0; j < 3; j = j + 1) { if (j == 1) continue; print i * 10 + j; }"
+        ),
+        "0\n2\n10\n12\n"
+    );
+}
+
+const GRADE: &str = "fun grade(n) { \
+    switch (n) { case 1: print \"one\"; case 1 + 1: print \"two\"; default: print \"many\"; } \
+}";
+
+#[test]
+fn switch_runs_the_first_matching_case() {
+    assert_eq!(
+        output(&format!("{GRADE} grade(1); grade(2);")),
+        "one\ntwo\n"
+    );
+    assert_eq!(
+        output("switch (\"b\") { case \"a\": print 1; case \"b\": print 2; case \"b\": print 3; }"),
+        "2\n"
+    );
+}
+
+#[test]
+fn switch_runs_the_default_when_no_case_matches() {
+    assert_eq!(
+        output(&format!("{GRADE} grade(3); grade(nil);")),
+        "many\nmany\n"
+    );
+}
+
+#[test]
+fn switch_with_no_match_and_no_default_does_nothing() {
+    assert_eq!(
+        output("switch (3) { case 1: print 1; case 2: print 2; } print \"after\";"),
+        "after\n"
+    );
+    assert_eq!(output("switch (1) {} print \"after\";"), "after\n");
+}
+
+#[test]
+fn switch_cases_do_not_fall_through() {
+    assert_eq!(
+        output("switch (1) { case 1: print 1; case 2: print 2; default: print 3; }"),
+        "1\n"
+    );
+}
+
+#[test]
+fn switch_stops_comparing_after_the_first_match() {
+    assert_eq!(
+        output(
+            "fun f(x) { print x; return x; } \
+             switch (1) { case f(0): print \"no\"; case f(1): print \"yes\"; case f(2): }"
+        ),
+        "0\n1\nyes\n"
+    );
+}
+
+#[test]
+fn switch_cases_have_their_own_scope() {
+    assert_eq!(
+        output(
+            "var x = \"outer\"; \
+             switch (1) { case 1: var x = \"inner\"; print x; } print x;"
+        ),
+        "inner\nouter\n"
+    );
+}
+
+#[test]
+fn break_in_a_switch_exits_the_enclosing_loop() {
+    assert_eq!(
+        output(
+            "for (var i = 0; i < 5; i = i + 1) { \
+                switch (i) { case 1: continue; case 3: break; } print i; \
+             }"
+        ),
+        "0\n2\n"
+    );
+}
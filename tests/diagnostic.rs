@@ -0,0 +1,78 @@
+mod common;
+
+use common::run;
+use rlox::diagnostic::{Diagnostic, Severity, Span};
+use rlox::LoxError;
+
+const PROGRAM: &str = "var greeting = \"hi\";\n\
+                       fun greet(name) {\n\
+                       \x20 print greeting + name\n\
+                       \x20 return name;\n\
+                       }";
+
+#[test]
+fn renders_a_compile_error_in_a_sample_program() {
+    let Err(LoxError::Compile(diagnostics)) = run(PROGRAM).1 else {
+        panic!("program should fail to compile");
+    };
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].render(PROGRAM),
+        "error: Expect ';'\n --> line 4:3\n  |\n4 |   return name;\n  |   ^^^^^^"
+    );
+}
+
+#[test]
+fn renders_a_runtime_error_underlining_its_whole_token() {
+    let source = "var a = nil;\nprint a and a.field;\nprint a or a.field;";
+    let Err(LoxError::Runtime(err)) = run(source).1 else {
+        panic!("program should fail at runtime");
+    };
+    assert_eq!(
+        Diagnostic::from(err).render(source),
+        "error: Only instances and classes have properties\n --> line 3:14\n  |\n\
+         3 | print a or a.field;\n  |              ^^^^^"
+    );
+}
+
+#[test]
+fn renders_a_warning() {
+    let span = Span {
+        line: 1,
+        column: 7,
+        len: 3,
+        start: 6,
+        byte_len: 3,
+    };
+    let warning = Diagnostic::warning("Unused variable 'foo'", span);
+    assert_eq!(warning.severity, Severity::Warning);
+    assert_eq!(
+        warning.render("{ var foo; }"),
+        "warning: Unused variable 'foo'\n --> line 1:7\n  |\n1 | { var foo; }\n  |       ^^^"
+    );
+}
+
+#[test]
+fn gutter_widens_for_lines_past_nine() {
+    let source = "\n".repeat(11) + "print (1;";
+    let Err(LoxError::Compile(diagnostics)) = run(&source).1 else {
+        panic!("program should fail to compile");
+    };
+    assert_eq!(
+        diagnostics[0].render(&source),
+        "error: Expect ')'\n  --> line 12:9\n   |\n12 | print (1;\n   |         ^"
+    );
+}
+
+#[test]
+fn span_at_the_end_of_the_source_renders_an_empty_line() {
+    let source = "print 1";
+    let Err(LoxError::Compile(diagnostics)) = run(source).1 else {
+        panic!("program should fail to compile");
+    };
+    assert_eq!(diagnostics[0].span.len, 0);
+    assert_eq!(
+        diagnostics[0].render(source),
+        "error: Expect ';'\n --> line 1:8\n  |\n1 | print 1\n  |        ^"
+    );
+}
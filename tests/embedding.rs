@@ -0,0 +1,217 @@
+mod common;
+
+use std::error::Error;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use common::{interpreter, runtime_error};
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::{LoxError, LoxValue, RuntimeError};
+
+#[test]
+fn runtime_errors_box_as_std_errors() {
+    let err: Box<dyn Error> = Box::new(runtime_error("var a = 1;\nprint a / 0;"));
+    assert_eq!(err.to_string(), "[line 2:9] Division by zero");
+}
+
+#[test]
+fn parser_errors_box_as_std_errors() {
+    let tokens = Scanner::new("print (1;".to_string()).scan_tokens().unwrap();
+    let errors = Parser::new(tokens).parse().unwrap_err();
+    let err: Box<dyn Error> = Box::new(errors[0].clone());
+    assert_eq!(err.to_string(), "[line 1:9] Error at ';': Expect ')'");
+}
+
+#[test]
+fn errors_from_run_source_work_with_the_question_mark_operator() {
+    fn run(source: &str) -> Result<(), Box<dyn Error>> {
+        let (mut interpreter, _) = interpreter();
+        rlox::run_source(&mut interpreter, source)?;
+        Ok(())
+    }
+    assert!(run("print 1;").is_ok());
+    assert_eq!(
+        run("print nil + 1;").unwrap_err().to_string(),
+        "[line 1:11] Operands must be two numbers or two strings for '+', got nil and number"
+    );
+    assert_eq!(
+        run("print (1;").unwrap_err().to_string(),
+        "[line 1:9] Expect ')'"
+    );
+}
+
+#[test]
+fn compile_errors_are_returned_with_their_spans() {
+    let (mut interpreter, out) = interpreter();
+    let result = rlox::run_source(&mut interpreter, "print 1;\nvar = 2;\nprint (3;");
+    let Err(LoxError::Compile(diagnostics)) = result else {
+        panic!("source should fail to compile, got {result:?}");
+    };
+    let spans = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.span.line, diagnostic.span.column))
+        .collect::<Vec<_>>();
+    assert_eq!(spans, [(2, 5), (3, 9)]);
+    assert_eq!(
+        out.contents(),
+        "",
+        "nothing should run after a compile error"
+    );
+}
+
+#[test]
+fn run_source_captures_print_output() {
+    let (mut interpreter, out) = interpreter();
+    rlox::run_source(&mut interpreter, "print 1+1;").unwrap();
+    assert_eq!(out.contents(), "2\n");
+}
+
+#[test]
+fn run_source_keeps_globals_between_calls() {
+    let (mut interpreter, out) = interpreter();
+    rlox::run_source(&mut interpreter, "var greeting = \"hi\";").unwrap();
+    rlox::run_source(&mut interpreter, "print greeting;").unwrap();
+    assert_eq!(out.contents(), "hi\n");
+}
+
+#[test]
+fn output_of_a_multi_line_program_goes_to_the_writer() {
+    let (mut interpreter, out) = interpreter();
+    rlox::run_source(
+        &mut interpreter,
+        "print \"first\";\nfor (var i = 1; i <= 2; i = i + 1) print i;\nprint nil;",
+    )
+    .unwrap();
+    assert_eq!(out.contents().as_bytes(), b"first\n1\n2\nnil\n");
+}
+
+#[test]
+fn scripts_can_call_natives_defined_by_the_host() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.define_native("double", 1, |paren, arguments| match arguments[0] {
+        LoxValue::Number(n) => Ok(LoxValue::Number(n * 2.0)),
+        ref other => Err(RuntimeError::invalid_argument(
+            paren, "double", "a number", other,
+        )),
+    });
+    rlox::run_source(&mut interpreter, "print double(21);").unwrap();
+    assert_eq!(out.contents(), "42\n");
+
+    let result = rlox::run_source(&mut interpreter, "double(1, 2);");
+    assert!(matches!(
+        result,
+        Err(LoxError::Runtime(RuntimeError::ArityMismatch(_, 1, 2)))
+    ));
+    let result = rlox::run_source(&mut interpreter, "double(\"x\");");
+    let Err(LoxError::Runtime(err @ RuntimeError::InvalidArgument(..))) = result else {
+        panic!("double should reject a string, got {result:?}");
+    };
+    assert_eq!(err.message(), "'double' expects a number, got string");
+}
+
+#[test]
+fn reset_forgets_everything_the_scripts_defined() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.define_native("host", 0, |_, _| Ok(LoxValue::Nil));
+    rlox::run_source(&mut interpreter, "var a = 1; fun f() {} class C {}").unwrap();
+    interpreter.reset();
+    for name in ["a", "f", "C", "host"] {
+        let result = rlox::run_source(&mut interpreter, &format!("print {name};"));
+        assert!(matches!(
+            result,
+            Err(LoxError::Runtime(RuntimeError::UndefinedVariable(_)))
+        ));
+    }
+    rlox::run_source(&mut interpreter, "print clock() > 0; print len(\"ab\");").unwrap();
+    assert_eq!(out.contents(), "true\n2\n");
+}
+
+#[test]
+fn a_clock_redefined_by_a_script_is_restored_by_reset() {
+    let (mut interpreter, out) = interpreter();
+    rlox::run_source(&mut interpreter, "var clock = 1;").unwrap();
+    interpreter.reset();
+    rlox::run_source(&mut interpreter, "print type(clock);").unwrap();
+    assert_eq!(out.contents(), "function\n");
+}
+
+#[test]
+fn a_step_budget_stops_an_infinite_loop() {
+    let (mut interpreter, _) = interpreter();
+    interpreter.set_max_steps(Some(1000));
+    let result = rlox::run_source(&mut interpreter, "while (true) {}");
+    assert!(matches!(
+        result,
+        Err(LoxError::Runtime(RuntimeError::BudgetExceeded(_)))
+    ));
+}
+
+#[test]
+fn a_step_budget_stops_unbounded_recursion_through_calls() {
+    let (mut interpreter, _) = interpreter();
+    interpreter.set_max_steps(Some(100));
+    let result = rlox::run_source(&mut interpreter, "fun f() { f(); }\nf();");
+    assert!(matches!(
+        result,
+        Err(LoxError::Runtime(RuntimeError::BudgetExceeded(_)))
+    ));
+}
+
+#[test]
+fn a_script_within_its_step_budget_runs_to_the_end() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_max_steps(Some(1000));
+    rlox::run_source(
+        &mut interpreter,
+        "for (var i = 0; i < 3; i = i + 1) print i;",
+    )
+    .unwrap();
+    assert_eq!(out.contents(), "0\n1\n2\n");
+}
+
+#[test]
+fn the_step_budget_is_shared_by_every_run_until_reset() {
+    let (mut interpreter, _) = interpreter();
+    interpreter.set_max_steps(Some(200));
+    let source = "for (var i = 0; i < 10; i = i + 1) {}";
+    let results: Vec<_> = (0..20)
+        .map(|_| rlox::run_source(&mut interpreter, source).is_ok())
+        .collect();
+    assert!(results[0]);
+    assert!(!results[19]);
+    interpreter.reset();
+    assert!(rlox::run_source(&mut interpreter, source).is_ok());
+}
+
+#[test]
+fn setting_the_cancellation_flag_from_another_thread_stops_a_busy_loop() {
+    let (mut interpreter, _) = interpreter();
+    let cancelled = interpreter.cancellation_token();
+    let timer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        cancelled.store(true, Ordering::Relaxed);
+    });
+    let result = rlox::run_source(&mut interpreter, "var i = 0;\nwhile (true) { i = i + 1; }");
+    timer.join().unwrap();
+    let Err(LoxError::Runtime(RuntimeError::Cancelled(token))) = result else {
+        panic!("the loop should be cancelled");
+    };
+    assert_eq!(token.lexeme(), "while");
+}
+
+#[test]
+fn a_cancelled_interpreter_runs_again_once_the_flag_is_cleared() {
+    let (mut interpreter, out) = interpreter();
+    let cancelled = interpreter.cancellation_token();
+    cancelled.store(true, Ordering::Relaxed);
+    let result = rlox::run_source(&mut interpreter, "fun f() {} f();");
+    assert!(matches!(
+        result,
+        Err(LoxError::Runtime(RuntimeError::Cancelled(_)))
+    ));
+    cancelled.store(false, Ordering::Relaxed);
+    rlox::run_source(&mut interpreter, "f(); print \"ran\";").unwrap();
+    assert_eq!(out.contents(), "ran\n");
+}
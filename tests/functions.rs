@@ -0,0 +1,191 @@
+mod common;
+
+use common::{output, run, runtime_error};
+use rlox::RuntimeError;
+
+#[test]
+fn zero_argument_function() {
+    assert_eq!(
+        output("fun hello() { print \"hello\"; } hello();"),
+        "hello\n"
+    );
+}
+
+#[test]
+fn recursive_fib() {
+    assert_eq!(
+        output(
+            "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } print fib(15);"
+        ),
+        "610\n"
+    );
+}
+
+#[test]
+fn calling_with_the_wrong_number_of_arguments() {
+    let err = runtime_error("fun add(a, b) { return a + b; }\nadd(1);");
+    assert!(matches!(err, RuntimeError::ArityMismatch(_, 2, 1)));
+    assert_eq!(err.token().line(), 2);
+    assert_eq!(err.message(), "Expected 2 arguments but got 1");
+}
+
+#[test]
+fn return_exits_a_loop_early() {
+    assert_eq!(
+        output(
+            "fun firstOver(limit) {\n\
+               for (var i = 0; i < 100; i = i + 1) { if (i > limit) return i; }\n\
+               return -1;\n\
+             }\n\
+             print firstOver(4);"
+        ),
+        "5\n"
+    );
+}
+
+#[test]
+fn return_exits_nested_blocks() {
+    assert_eq!(
+        output("fun f() { { { return \"inner\"; } } return \"outer\"; } print f();"),
+        "inner\n"
+    );
+}
+
+#[test]
+fn bare_return_and_falling_off_the_end_give_nil() {
+    assert_eq!(output("fun f() { return; } print f();"), "nil\n");
+    assert_eq!(output("fun g() {} print g();"), "nil\n");
+}
+
+#[test]
+fn counters_from_the_same_maker_do_not_share_state() {
+    assert_eq!(
+        output(
+            "fun makeCounter() {\n\
+               var i = 0;\n\
+               fun count() { i = i + 1; return i; }\n\
+               return count;\n\
+             }\n\
+             var a = makeCounter();\n\
+             var b = makeCounter();\n\
+             print a(); print a(); print b();"
+        ),
+        "1\n2\n1\n"
+    );
+}
+
+#[test]
+fn closures_bind_the_variable_in_scope_where_they_are_declared() {
+    assert_eq!(
+        output(
+            "var a = \"global\";\n\
+             {\n\
+               fun showA() { print a; }\n\
+               showA();\n\
+               var a = \"block\";\n\
+               showA();\n\
+             }"
+        ),
+        "global\nglobal\n"
+    );
+}
+
+#[test]
+fn calling_a_lambda_inline() {
+    assert_eq!(output("print fun (x) { return x * 2; }(21);"), "42\n");
+}
+
+#[test]
+fn storing_a_lambda_and_calling_it_later() {
+    assert_eq!(
+        output("var add = fun (a, b) { return a + b; }; print add(1, 2); print add;"),
+        "3\n<fn>\n"
+    );
+}
+
+#[test]
+fn passing_a_lambda_as_an_argument() {
+    assert_eq!(
+        output("fun twice(f, x) { return f(f(x)); } print twice(fun (n) { return n + 3; }, 1);"),
+        "7\n"
+    );
+}
+
+#[test]
+fn lambdas_close_over_their_scope() {
+    assert_eq!(
+        output(
+            "fun adder(n) { return fun (x) { return x + n; }; }\n\
+             var add5 = adder(5); print add5(1); print adder(10)(1);"
+        ),
+        "6\n11\n"
+    );
+}
+
+#[test]
+fn a_lambda_is_not_bound_to_a_name() {
+    let err = runtime_error("var f = fun () { return 1; }; fun () {}; print f() + g;");
+    assert!(matches!(err, RuntimeError::UndefinedVariable(_)));
+    assert_eq!(err.token().lexeme(), "g");
+}
+
+#[test]
+fn too_many_arguments_are_reported_at_the_closing_paren() {
+    let err = runtime_error("fun f(a, b) {}\nf(\n  1,\n  2,\n  3\n);");
+    assert_eq!(err.message(), "Expected 2 arguments but got 3");
+    assert_eq!(
+        (err.token().lexeme().as_str(), err.token().line()),
+        (")", 6)
+    );
+}
+
+#[test]
+fn natives_and_classes_check_arity_the_same_way() {
+    assert_eq!(
+        runtime_error("clock(1);").message(),
+        "Expected 0 arguments but got 1"
+    );
+    assert_eq!(
+        runtime_error("class A { init(a, b) {} } A(1);").message(),
+        "Expected 2 arguments but got 1"
+    );
+    assert_eq!(
+        runtime_error("class A {} A(1);").message(),
+        "Expected 0 arguments but got 1"
+    );
+}
+
+#[test]
+fn calling_a_value_that_is_not_callable() {
+    for source in [
+        "3();",
+        "\"s\"();",
+        "nil();",
+        "true();",
+        "[]();",
+        "class A {} A()();",
+    ] {
+        let err = runtime_error(source);
+        assert!(matches!(err, RuntimeError::NotCallable(_)), "{source:?}");
+        assert_eq!(err.message(), "Can only call functions and classes");
+        assert_eq!(err.token().lexeme(), ")");
+    }
+}
+
+#[test]
+fn not_callable_is_checked_before_arity() {
+    let err = runtime_error("var x = 3;\nx(1, 2);");
+    assert!(matches!(err, RuntimeError::NotCallable(_)));
+    assert_eq!(err.token().line(), 2);
+}
+
+#[test]
+fn arguments_are_evaluated_before_the_callee_is_found_not_callable() {
+    let (out, _) = run("var x = 3; fun f() { print \"arg\"; } x(f());");
+    assert_eq!(out, "arg\n");
+}
+
+#[test]
+fn calling_a_real_function_still_works() {
+    assert_eq!(output("var x = fun () { return 3; }; print x();"), "3\n");
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{interpreter, on_large_stack, output, run, runtime_error};
+use rlox::{LoxError, RuntimeError};
+
+/// Runs `f` on a thread with the default 2MB stack, as a host embedding rlox might
+fn on_spawned_thread(f: impl FnOnce() + Send + 'static) {
+    thread::spawn(f).join().expect("test should pass");
+}
+
+#[test]
+fn mutual_recursion_raises_stack_overflow_at_the_call() {
+    on_spawned_thread(|| {
+        let err = runtime_error(
+            "fun isEven(n) { if (n == 0) return true; return isOdd(n - 1); }\n\
+             fun isOdd(n) { if (n == 0) return false; return isEven(n - 1); }\n\
+             isEven(100000);",
+        );
+        assert!(matches!(err, RuntimeError::StackOverflow(_)));
+        // The 64th call is to isOdd, whose call to isEven is one too many
+        assert_eq!(err.token().line(), 2);
+    });
+}
+
+#[test]
+fn the_default_call_depth_fits_the_stack_of_a_spawned_thread() {
+    let sources = [
+        "fun f(n) { return f(n + 1); } f(0);",
+        "fun f(n) { return 1 + f(n + 1) * 2; } f(0);",
+        "fun f(n) { for (var i = 0; i < 3; i = i + 1) { if (i == 1) { { print f(n + 1); } } } } \
+         f(0);",
+        "class A { init(n) { this.next = A(n + 1); } } A(0);",
+        "class A { m(n) { return [this.m(n + 1)]; } } A().m(0);",
+        "var f = fun (n) { switch (n) { default: return {\"k\": f(n + 1)}; } }; f(0);",
+    ];
+    for source in sources {
+        on_spawned_thread(move || {
+            let err = runtime_error(source);
+            assert!(matches!(err, RuntimeError::StackOverflow(_)), "{source}");
+        });
+    }
+}
+
+#[test]
+fn call_depth_is_restored_after_a_stack_overflow() {
+    on_spawned_thread(|| {
+        let (mut interpreter, out) = interpreter();
+        let result = rlox::run_source(&mut interpreter, "fun f(n) { return f(n + 1); } f(0);");
+        assert!(matches!(
+            result,
+            Err(LoxError::Runtime(RuntimeError::StackOverflow(_)))
+        ));
+        let source = "fun g(n) { if (n > 0) return g(n - 1); return n; } print g(60);";
+        rlox::run_source(&mut interpreter, source).unwrap();
+        assert_eq!(out.contents(), "0\n");
+    });
+}
+
+#[test]
+fn max_call_depth_can_be_raised_for_large_stacks() {
+    on_large_stack(|| {
+        let (mut interpreter, out) = interpreter();
+        interpreter.set_max_call_depth(1000);
+        let source = "fun g(n) { if (n > 0) return g(n - 1); return n; } print g(999);";
+        rlox::run_source(&mut interpreter, source).unwrap();
+        assert_eq!(out.contents(), "0\n");
+        let result = rlox::run_source(&mut interpreter, "g(1000);");
+        assert!(matches!(
+            result,
+            Err(LoxError::Runtime(RuntimeError::StackOverflow(_)))
+        ));
+    });
+}
+
+#[test]
+fn max_call_depth_can_be_lowered_for_small_stacks() {
+    let (mut interpreter, _) = interpreter();
+    interpreter.set_max_call_depth(10);
+    let result = rlox::run_source(
+        &mut interpreter,
+        "fun f(n) { if (n > 0) return f(n - 1); return n; } f(20);",
+    );
+    assert!(matches!(
+        result,
+        Err(LoxError::Runtime(RuntimeError::StackOverflow(_)))
+    ));
+}
+
+#[test]
+fn str_and_write_use_to_string_like_print() {
+    let out = output(
+        "class Point {\n\
+           init(x, y) { this.x = x; this.y = y; }\n\
+           toString() { return \"(\" + str(this.x) + \", \" + str(this.y) + \")\"; }\n\
+         }\n\
+         var p = Point(1, 2);\n\
+         print p;\n\
+         print \"at \" + str(p);\n\
+         write(p);\n\
+         print \"\";",
+    );
+    assert_eq!(out, "(1, 2)\nat (1, 2)\n(1, 2)\n");
+}
+
+/// Output that can never be written, like a full disk
+struct FullDisk;
+
+impl Write for FullDisk {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::StorageFull.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_writes_are_runtime_errors() {
+    for source in ["print 1;", "\nwrite(1);"] {
+        let mut interpreter = rlox::Interpreter::with_writer(Box::new(FullDisk));
+        let result = rlox::run_source(&mut interpreter, source);
+        let Err(LoxError::Runtime(err @ RuntimeError::WriteFailed(_))) = result else {
+            panic!("{source:?} should fail to write, but got {result:?}");
+        };
+        assert_eq!(err.token().line(), source.lines().count());
+    }
+}
+
+#[test]
+fn star_says_a_string_can_be_repeated() {
+    assert_eq!(
+        runtime_error("print \"ab\" * 1.5;").message(),
+        "Operands must be two numbers or a string and a non-negative whole number for '*', \
+         got string and number"
+    );
+    assert_eq!(
+        runtime_error("print 2 - \"ab\";").message(),
+        "Operands must be numbers for '-', got number and string"
+    );
+}
+
+#[test]
+fn expression_statements_print_nothing_in_a_script() {
+    assert_eq!(output("1 + 1;"), "");
+}
+
+#[test]
+fn statements_after_a_runtime_error_in_a_block_do_not_run() {
+    let (out, result) =
+        run("print \"before\";\n{ print undefinedVar; print \"after\"; }\nprint \"outside\";");
+    assert_eq!(out, "before\n");
+    let Err(LoxError::Runtime(err)) = result else {
+        panic!("program should fail at runtime");
+    };
+    assert!(matches!(err, RuntimeError::UndefinedVariable(_)));
+    assert_eq!(err.token().line(), 2);
+}
+
+#[test]
+fn a_runtime_error_in_a_nested_block_inside_a_function_stops_everything() {
+    let (out, result) =
+        run("fun f() { { { print 1; nil + 1; print 2; } print 3; } print 4; }\nf(); print 5;");
+    assert_eq!(out, "1\n");
+    assert!(matches!(result, Err(LoxError::Runtime(_))));
+}
+
+#[test]
+fn a_runtime_error_stops_the_statements_after_it() {
+    let (mut interpreter, out) = interpreter();
+    let result = rlox::run_source(
+        &mut interpreter,
+        "var x = 1;\nx = nil + 1; x = 2;\nprint x;",
+    );
+    assert!(matches!(result, Err(LoxError::Runtime(_))));
+    assert_eq!(out.contents(), "");
+    // The failed statement left x as it was, and the later assignment never ran
+    rlox::run_source(&mut interpreter, "print x;").unwrap();
+    assert_eq!(out.contents(), "1\n");
+}
+
+/// Fastest of a few runs, so a busy machine doesn't skew the comparison
+fn time_to_run(source: &str) -> Duration {
+    (0..3)
+        .map(|_| {
+            let (mut interpreter, _) = interpreter();
+            let start = Instant::now();
+            rlox::run_source(&mut interpreter, source).unwrap();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// Locals are found by the slot the resolver gives them, globals by name
+#[test]
+#[ignore = "benchmark, run with --release -- --ignored --nocapture"]
+fn counting_loop_with_locals_is_faster_than_with_globals() {
+    let locals =
+        time_to_run("fun count() { var i = 0; while (i < 2000000) i = i + 1; return i; } count();");
+    let globals = time_to_run("var i = 0; while (i < 2000000) i = i + 1;");
+    println!("counting to 2M took {locals:?} with locals and {globals:?} with globals");
+    assert!(locals < globals);
+}
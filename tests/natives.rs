@@ -0,0 +1,362 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{output, run, runtime_error, SharedBuffer};
+use rlox::{Interpreter, LoxError, RuntimeError};
+
+/// Runs `source` with `input` as what `read_line` reads, returning what it printed
+fn output_reading(input: &str, source: &str) -> String {
+    let out = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_io(
+        Box::new(Cursor::new(input.to_string())),
+        Box::new(out.clone()),
+    );
+    rlox::run_source(&mut interpreter, source).expect("source should run");
+    out.contents()
+}
+
+#[test]
+fn clock_does_not_go_backwards() {
+    assert_eq!(
+        output("var a = clock(); var b = clock(); print b >= a and a > 0;"),
+        "true\n"
+    );
+}
+
+#[test]
+fn clock_takes_no_arguments() {
+    let err = runtime_error("clock(\"x\");");
+    assert!(matches!(err, RuntimeError::ArityMismatch(_, 0, 1)));
+}
+
+#[test]
+fn len_of_a_list_and_of_a_string_in_chars() {
+    assert_eq!(
+        output("print len([1, 2, 3]); print len([]); print len(\"héllo\");"),
+        "3\n0\n5\n"
+    );
+}
+
+#[test]
+fn push_appends_and_returns_nil() {
+    assert_eq!(
+        output("var xs = [1]; print push(xs, 2); push(xs, [3]); print xs; print len(xs);"),
+        "nil\n[1, 2, [3]]\n3\n"
+    );
+}
+
+#[test]
+fn pop_removes_and_returns_the_last_element() {
+    assert_eq!(
+        output("var xs = [1, 2]; print pop(xs); print xs;"),
+        "2\n[1]\n"
+    );
+}
+
+#[test]
+fn pop_from_an_empty_list() {
+    let err = runtime_error("var xs = [];\npop(xs);");
+    assert!(matches!(err, RuntimeError::PopFromEmptyList(_)));
+    assert_eq!(err.token().line(), 2);
+}
+
+#[test]
+fn list_natives_check_their_arguments() {
+    for source in ["len(1);", "push(\"a\", 1);", "pop(\"a\");"] {
+        assert!(matches!(
+            runtime_error(source),
+            RuntimeError::InvalidArgument(..)
+        ));
+    }
+    assert!(matches!(
+        runtime_error("push([]);"),
+        RuntimeError::ArityMismatch(_, 2, 1)
+    ));
+}
+
+#[test]
+fn type_names_each_kind_of_value() {
+    assert_eq!(
+        output(
+            "class A { m() {} }\n\
+             fun f() {}\n\
+             print type(nil); print type(true); print type(1.5); print type(\"s\");\n\
+             print type(f); print type(fun () {}); print type(clock); print type(A().m);\n\
+             print type(A); print type(A()); print type([]); print type({});"
+        ),
+        "nil\nbool\nnumber\nstring\n\
+         function\nfunction\nfunction\nfunction\n\
+         class\ninstance\nlist\nmap\n"
+    );
+}
+
+#[test]
+fn type_returns_a_string() {
+    assert_eq!(output("print type(type(1)) == \"string\";"), "true\n");
+}
+
+#[test]
+fn type_takes_one_argument() {
+    assert!(matches!(
+        runtime_error("type();"),
+        RuntimeError::ArityMismatch(_, 1, 0)
+    ));
+    assert!(matches!(
+        runtime_error("type(1, 2);"),
+        RuntimeError::ArityMismatch(_, 1, 2)
+    ));
+}
+
+#[test]
+fn number_parses_a_string() {
+    assert_eq!(
+        output("print number(\"3.14\"); print number(\" 2 \") * 2; print number(\"-1e3\");"),
+        "3.14\n4\n-1000\n"
+    );
+}
+
+#[test]
+fn number_of_a_number_is_unchanged() {
+    assert_eq!(output("print number(5) == 5;"), "true\n");
+}
+
+#[test]
+fn number_rejects_text_that_is_not_a_lox_number() {
+    for source in [
+        "number(\"abc\");",
+        "number(\"\");",
+        "number(\"inf\");",
+        "number(\"NaN\");",
+    ] {
+        assert!(matches!(
+            runtime_error(source),
+            RuntimeError::InvalidNumber(_)
+        ));
+    }
+    assert!(matches!(
+        runtime_error("number(nil);"),
+        RuntimeError::InvalidArgument(..)
+    ));
+}
+
+#[test]
+fn str_converts_any_value_as_print_would() {
+    assert_eq!(
+        output("print str(42) + \"!\"; print str(nil); print str(true); print str([1, \"a\"]);"),
+        "42!\nnil\ntrue\n[1, a]\n"
+    );
+    assert_eq!(
+        output("print str(\"s\") == \"s\"; print type(str(1.5));"),
+        "true\nstring\n"
+    );
+}
+
+#[test]
+fn read_line_returns_a_line_without_its_newline() {
+    assert_eq!(
+        output_reading("hello\n", "print read_line() + \"!\";"),
+        "hello!\n"
+    );
+    assert_eq!(output_reading("crlf\r\n", "print len(read_line());"), "4\n");
+}
+
+#[test]
+fn read_line_reads_successive_lines() {
+    assert_eq!(
+        output_reading("one\ntwo", "print read_line(); print read_line();"),
+        "one\ntwo\n"
+    );
+}
+
+#[test]
+fn read_line_returns_nil_at_the_end_of_input() {
+    assert_eq!(output_reading("", "print read_line();"), "nil\n");
+    assert_eq!(
+        output_reading("last\n", "read_line(); print read_line();"),
+        "nil\n"
+    );
+}
+
+#[test]
+fn write_adds_no_newline() {
+    assert_eq!(output("write(\"a\"); write(\"b\"); print \"c\";"), "abc\n");
+    assert_eq!(
+        output("write(1.5); write(nil); write([true]);"),
+        "1.5nil[true]"
+    );
+}
+
+#[test]
+fn write_returns_nil_and_takes_one_argument() {
+    assert_eq!(output("print write(\"\");"), "nil\n");
+    assert!(matches!(
+        runtime_error("write(\"a\", \"b\");"),
+        RuntimeError::ArityMismatch(_, 1, 2)
+    ));
+}
+
+#[test]
+fn passing_asserts_do_nothing() {
+    assert_eq!(
+        output(
+            "assert(1 == 1); assert(\"x\");\n\
+             assert_eq(1 + 1, 2); assert_eq(nil, nil); print \"ok\";"
+        ),
+        "ok\n"
+    );
+}
+
+#[test]
+fn a_failing_assert_stops_the_script() {
+    let (out, result) = run("print 1;\nassert(nil);\nprint 2;");
+    assert_eq!(out, "1\n");
+    let Err(LoxError::Runtime(err)) = result else {
+        panic!("assert should fail");
+    };
+    assert!(matches!(err, RuntimeError::AssertionFailed(_)));
+    assert_eq!(err.token().line(), 2);
+}
+
+#[test]
+fn a_failing_assert_eq_names_both_operands() {
+    let err = runtime_error("assert_eq(1 + 1, 3);");
+    assert!(matches!(err, RuntimeError::AssertionNotEqual(..)));
+    assert_eq!(err.message(), "Assertion failed: 2 != 3");
+}
+
+#[test]
+fn fmt_rounds_to_the_given_decimal_places() {
+    assert_eq!(
+        output(
+            "print fmt(3.14159, 2); print fmt(2.71828, 3);\n\
+             print fmt(9.99, 1); print fmt(123.4, 0);"
+        ),
+        "3.14\n2.718\n10.0\n123\n"
+    );
+}
+
+#[test]
+fn fmt_pads_with_trailing_zeros() {
+    assert_eq!(
+        output("print fmt(1, 3); print fmt(-0.5, 2);"),
+        "1.000\n-0.50\n"
+    );
+}
+
+#[test]
+fn fmt_returns_a_string() {
+    assert_eq!(output("print fmt(1, 1) + \"!\";"), "1.0!\n");
+}
+
+#[test]
+fn fmt_needs_a_non_negative_whole_number_of_decimals() {
+    for source in [
+        "fmt(1, -1);",
+        "fmt(1, 1.5);",
+        "fmt(1, \"2\");",
+        "fmt(\"1\", 2);",
+    ] {
+        assert!(matches!(
+            runtime_error(source),
+            RuntimeError::InvalidArgument(..)
+        ));
+    }
+}
+
+#[test]
+fn fmt_errors_say_what_decimals_it_takes() {
+    let cases = [
+        (
+            "fmt(1, -1);",
+            "'fmt' expects a whole number of decimal places from 0 to 100, got -1",
+        ),
+        (
+            "fmt(1, 1.5);",
+            "'fmt' expects a whole number of decimal places from 0 to 100, got 1.5",
+        ),
+        (
+            "fmt(1, 101);",
+            "'fmt' expects a whole number of decimal places from 0 to 100, got 101",
+        ),
+        (
+            "fmt(1, \"2\");",
+            "'fmt' expects a whole number of decimal places, got string",
+        ),
+        ("fmt(\"1\", 2);", "'fmt' expects a number, got string"),
+    ];
+    for (source, message) in cases {
+        assert_eq!(runtime_error(source).message(), message, "{source}");
+    }
+}
+
+#[test]
+fn math_natives_of_one_number() {
+    assert_eq!(
+        output(
+            "print abs(-2.5); print abs(3);\n\
+             print floor(2.7); print floor(-2.5);\n\
+             print ceil(2.1); print ceil(-2.5);\n\
+             print sqrt(16); print sqrt(2);"
+        ),
+        "2.5\n3\n2\n-3\n3\n-2\n4\n1.4142135623730951\n"
+    );
+}
+
+#[test]
+fn min_and_max_of_two_numbers() {
+    assert_eq!(
+        output("print min(1, 2); print min(2, -1); print max(1, 2); print max(-3, -4);"),
+        "1\n-1\n2\n-3\n"
+    );
+}
+
+#[test]
+fn sqrt_of_a_negative_number_is_an_error() {
+    let err = runtime_error("print sqrt(4);\nsqrt(-1);");
+    assert!(matches!(err, RuntimeError::SqrtOfNegative(_)));
+    assert_eq!(err.token().line(), 2);
+    assert_eq!(
+        err.message(),
+        "Can't take the square root of a negative number"
+    );
+}
+
+#[test]
+fn math_natives_reject_non_numbers() {
+    for source in [
+        "abs(\"a\");",
+        "floor(nil);",
+        "ceil(true);",
+        "sqrt([]);",
+        "min(1, nil);",
+        "max(\"a\", 1);",
+    ] {
+        assert!(
+            matches!(runtime_error(source), RuntimeError::InvalidArgument(..)),
+            "{source:?}"
+        );
+    }
+}
+
+#[test]
+fn invalid_argument_messages_name_the_native_and_what_it_got() {
+    let cases = [
+        ("abs(\"a\");", "'abs' expects a number, got string"),
+        ("floor(nil);", "'floor' expects a number, got nil"),
+        ("ceil(true);", "'ceil' expects a number, got bool"),
+        ("sqrt([]);", "'sqrt' expects a number, got list"),
+        ("min(1, {});", "'min' expects a number, got map"),
+        ("max(\"a\", 1);", "'max' expects a number, got string"),
+        ("len(1);", "'len' expects a list, map or string, got number"),
+        ("push(\"a\", 1);", "'push' expects a list, got string"),
+        ("pop(nil);", "'pop' expects a list, got nil"),
+        (
+            "number(true);",
+            "'number' expects a number or a string, got bool",
+        ),
+    ];
+    for (source, message) in cases {
+        assert_eq!(runtime_error(source).message(), message, "{source}");
+    }
+}
@@ -0,0 +1,306 @@
+mod common;
+
+use common::{compile_errors, interpreter, output, runtime_error};
+use rlox::{LoxError, RuntimeError};
+
+#[test]
+fn plus_adds_two_numbers() {
+    assert_eq!(output("print 2 + 3;"), "5\n");
+    assert_eq!(output("print -1.5 + 0.25;"), "-1.25\n");
+}
+
+#[test]
+fn plus_concatenates_two_strings() {
+    assert_eq!(output("print \"foo\" + \"bar\";"), "foobar\n");
+}
+
+#[test]
+fn plus_rejects_a_string_and_a_number_in_either_order() {
+    for source in ["print \"a\" + 1;", "print 1 + \"a\";"] {
+        let err = runtime_error(source);
+        assert!(matches!(err, RuntimeError::InvalidBinaryOperand(..)));
+        assert_eq!(err.token().lexeme(), "+");
+    }
+}
+
+#[test]
+fn plus_rejects_operands_that_are_neither_numbers_nor_strings() {
+    let err = runtime_error("print nil + true;");
+    assert!(matches!(
+        err,
+        RuntimeError::InvalidBinaryOperand(_, "nil", "bool")
+    ));
+}
+
+#[test]
+fn dividing_by_zero_is_an_error_at_the_operator() {
+    for source in ["var x = 1 / 0;", "var x = 0 / 0;", "var x = -5 / 0;"] {
+        let (mut interpreter, _) = interpreter();
+        let result = rlox::run_source(&mut interpreter, source);
+        let Err(LoxError::Runtime(err @ RuntimeError::DivisionByZero(_))) = result else {
+            panic!("{source:?} should divide by zero, got {result:?}");
+        };
+        assert_eq!(err.token().lexeme(), "/");
+        // No inf or NaN was assigned
+        let result = rlox::run_source(&mut interpreter, "print x;");
+        assert!(matches!(
+            result,
+            Err(LoxError::Runtime(RuntimeError::UndefinedVariable(_)))
+        ));
+    }
+}
+
+#[test]
+fn dividing_by_a_non_zero_number() {
+    assert_eq!(output("print 7 / 2; print -6 / 3;"), "3.5\n-2\n");
+}
+
+#[test]
+fn modulo_of_two_numbers() {
+    assert_eq!(
+        output("print 7 % 3; print 7.5 % 2; print -7 % 3;"),
+        "1\n1.5\n-1\n"
+    );
+}
+
+#[test]
+fn modulo_by_zero_is_an_error() {
+    let err = runtime_error("print 7 % 0;");
+    assert!(matches!(err, RuntimeError::DivisionByZero(_)));
+    assert_eq!(err.token().lexeme(), "%");
+}
+
+#[test]
+fn modulo_rejects_non_numbers() {
+    let err = runtime_error("print \"7\" % 2;");
+    assert!(matches!(err, RuntimeError::InvalidBinaryOperand(..)));
+}
+
+#[test]
+fn strings_compare_lexicographically() {
+    assert_eq!(
+        output(
+            "print \"apple\" < \"banana\"; print \"b\" >= \"b\"; \
+             print \"b\" > \"abc\"; print \"Z\" <= \"a\";"
+        ),
+        "true\ntrue\ntrue\ntrue\n"
+    );
+}
+
+#[test]
+fn comparing_a_string_with_a_number_is_an_error() {
+    for source in ["print \"1\" < 2;", "print 2 >= \"1\";"] {
+        let err = runtime_error(source);
+        assert!(matches!(err, RuntimeError::InvalidBinaryOperand(..)));
+    }
+}
+
+#[test]
+fn ternary_picks_a_branch_by_truthiness() {
+    assert_eq!(
+        output("print true ? 1 : 2; print nil ? 1 : 2; print 0 ? \"yes\" : \"no\";"),
+        "1\n2\nyes\n"
+    );
+}
+
+#[test]
+fn ternary_skips_the_branch_not_taken() {
+    // Either untaken branch would be a runtime error
+    assert_eq!(
+        output("print true ? 1 : nil + 1; print false ? undefined : 2;"),
+        "1\n2\n"
+    );
+    assert_eq!(
+        output("var n = 0; fun bump() { n = n + 1; } true ? 0 : bump(); print n;"),
+        "0\n"
+    );
+}
+
+#[test]
+fn star_repeats_a_string_on_either_side() {
+    assert_eq!(
+        output("print \"ab\" * 3; print 3 * \"x\"; print \"y\" * 0 + \"|\";"),
+        "ababab\nxxx\n|\n"
+    );
+}
+
+#[test]
+fn star_rejects_a_fractional_or_negative_count() {
+    for source in [
+        "print \"ab\" * 1.5;",
+        "print -1 * \"ab\";",
+        "print \"ab\" * \"c\";",
+    ] {
+        let err = runtime_error(source);
+        assert!(matches!(err, RuntimeError::InvalidBinaryOperand(..)));
+        assert_eq!(err.token().lexeme(), "*");
+    }
+}
+
+#[test]
+fn star_refuses_to_build_a_huge_string() {
+    for source in ["print \"ab\" * 1e9;", "print 1e300 * \"ab\";"] {
+        let err = runtime_error(source);
+        assert!(matches!(err, RuntimeError::StringTooLong(_)));
+        assert_eq!(err.message(), "Repeated string is too long");
+    }
+}
+
+#[test]
+fn star_still_multiplies_numbers() {
+    assert_eq!(output("print 2 * 3.5; print -2 * 1e9;"), "7\n-2000000000\n");
+}
+
+#[test]
+fn compound_assignment_operators() {
+    assert_eq!(
+        output("var i = 1; i += 4; print i; i -= 1; print i; i *= 3; print i; i /= 2; print i;"),
+        "5\n4\n12\n6\n"
+    );
+    assert_eq!(
+        output("var s = \"a\"; print s += \"b\"; print s;"),
+        "ab\nab\n"
+    );
+}
+
+#[test]
+fn compound_assignment_needs_a_variable_on_the_left() {
+    assert_eq!(
+        compile_errors("1 += 2; (a) -= 1;"),
+        ["Invalid assignment target", "Invalid assignment target"]
+    );
+}
+
+#[test]
+fn bitwise_operators_on_whole_numbers() {
+    assert_eq!(
+        output("print 5 & 3; print 5 | 3; print 5 ^ 3; print 1 << 4; print -16 >> 2;"),
+        "1\n7\n6\n16\n-4\n"
+    );
+    assert_eq!(output("print ~0; print ~5;"), "-1\n-6\n");
+}
+
+#[test]
+fn bitwise_operators_reject_fractions_and_non_numbers() {
+    for source in ["print 1.5 & 1;", "print 1 | \"a\";", "print 1 << 64;"] {
+        let err = runtime_error(source);
+        assert!(matches!(err, RuntimeError::InvalidBinaryOperand(..)));
+    }
+    assert!(matches!(
+        runtime_error("print ~0.5;"),
+        RuntimeError::InvalidUnaryOperand(_)
+    ));
+}
+
+#[test]
+fn invalid_operand_messages_name_the_operator_and_operand_types() {
+    let cases = [
+        (
+            "print 1 + \"x\";",
+            "Operands must be two numbers or two strings for '+', got number and string",
+        ),
+        (
+            "print \"a\" - \"b\";",
+            "Operands must be numbers for '-', got string and string",
+        ),
+        (
+            "print nil / {};",
+            "Operands must be numbers for '/', got nil and map",
+        ),
+        (
+            "print [] < 1;",
+            "Operands must be two numbers or two strings for '<', got list and number",
+        ),
+        (
+            "print 1.5 & true;",
+            "Operands must be whole numbers that fit in 64 bits for '&', got number and bool",
+        ),
+    ];
+    for (source, message) in cases {
+        assert_eq!(runtime_error(source).message(), message);
+    }
+}
+
+#[test]
+fn relational_operators_only_take_two_numbers_or_two_strings() {
+    let operands = [
+        ("true", "bool"),
+        ("nil", "nil"),
+        ("1", "number"),
+        ("\"a\"", "string"),
+    ];
+    for operator in ["<", ">", "<=", ">="] {
+        for (left, left_type) in operands {
+            for (right, right_type) in operands {
+                let source = format!("print {left} {operator} {right};");
+                if left_type == right_type && matches!(left_type, "number" | "string") {
+                    // Each operand equals itself, so only the non-strict operators hold
+                    let expected = if operator.ends_with('=') {
+                        "true\n"
+                    } else {
+                        "false\n"
+                    };
+                    assert_eq!(output(&source), expected);
+                    continue;
+                }
+                let err = runtime_error(&source);
+                assert!(
+                    matches!(err, RuntimeError::InvalidBinaryOperand(..)),
+                    "{source}"
+                );
+                assert_eq!(err.token().lexeme(), operator);
+                assert_eq!(
+                    err.message(),
+                    format!(
+                        "Operands must be two numbers or two strings for '{operator}', \
+                         got {left_type} and {right_type}"
+                    )
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn equality_works_between_any_types_unlike_comparison() {
+    assert_eq!(
+        output("print true == false; print nil == nil; print true != nil; print 1 == \"1\";"),
+        "false\ntrue\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn postfix_increments_evaluate_to_the_old_value_and_prefix_to_the_new() {
+    assert_eq!(
+        output("var i = 1; print i++; print i; print ++i; print i;"),
+        "1\n2\n3\n3\n"
+    );
+    assert_eq!(
+        output("var i = 1; print i--; print i; print --i; print i;"),
+        "1\n0\n-1\n-1\n"
+    );
+}
+
+#[test]
+fn increments_in_a_for_loop_and_a_closure() {
+    assert_eq!(output("for (var i = 0; i < 3; i++) print i;"), "0\n1\n2\n");
+    assert_eq!(
+        output(
+            "fun counter() { var n = 0; return fun () { return ++n; }; } \
+             var c = counter(); c(); print c();"
+        ),
+        "2\n"
+    );
+}
+
+#[test]
+fn incrementing_a_non_number_is_an_error_at_the_operator() {
+    for source in ["var s = \"a\"; s++;", "var n; --n;"] {
+        let err = runtime_error(source);
+        assert!(
+            matches!(err, RuntimeError::InvalidUnaryOperand(_)),
+            "{source}"
+        );
+        assert_eq!(err.token().lexeme().len(), 2);
+    }
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use common::{ast, compile_errors};
+
+#[test]
+fn for_desugars_into_a_while_in_a_block() {
+    assert_eq!(
+        ast("for (var i = 0; i < 3; i = i + 1) print i;"),
+        "(block\n  (var i 0)\n  (for (< i 3) (= i (+ i 1))\n    (print i)))"
+    );
+}
+
+#[test]
+fn for_without_an_initializer_is_not_wrapped_in_a_block() {
+    assert_eq!(
+        ast("for (; i < 3; i = i + 1) print i;"),
+        "(for (< i 3) (= i (+ i 1))\n  (print i))"
+    );
+}
+
+#[test]
+fn for_without_a_condition_loops_while_true() {
+    assert_eq!(
+        ast("for (i = 0;;) print i;"),
+        "(block\n  (expr (= i 0))\n  (while true\n    (print i)))"
+    );
+    assert_eq!(ast("for (;;) {}"), "(while true\n  (block))");
+}
+
+#[test]
+fn modulo_binds_tighter_than_plus() {
+    assert_eq!(ast("1 + 7 % 3;"), "(expr (+ 1 (% 7 3)))");
+    assert_eq!(ast("7 % 3 * 2;"), "(expr (* (% 7 3) 2))");
+}
+
+#[test]
+fn errors_on_the_same_line_have_their_own_columns() {
+    let (_, result) = common::run("print (1; print (2;");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "[line 1:9] Expect ')'\n[line 1:19] Expect ')'"
+    );
+}
+
+#[test]
+fn ternary_binds_looser_than_equality() {
+    assert_eq!(
+        ast("a == b ? c : d == e;"),
+        "(expr (? (== a b) c (== d e)))"
+    );
+}
+
+#[test]
+fn ternary_is_right_associative() {
+    assert_eq!(ast("a ? b : c ? d : e;"), "(expr (? a b (? c d e)))");
+    assert_eq!(ast("a ? b ? c : d : e;"), "(expr (? a (? b c d) e))");
+}
+
+#[test]
+fn ternary_binds_tighter_than_assignment() {
+    assert_eq!(ast("x = a ? b : c;"), "(expr (= x (? a b c)))");
+}
+
+#[test]
+fn compound_assignment_desugars_into_an_assignment() {
+    assert_eq!(ast("i += 4;"), "(expr (= i (+ i 4)))");
+    assert_eq!(ast("i -= 4;"), "(expr (= i (- i 4)))");
+    assert_eq!(ast("i *= 4;"), "(expr (= i (* i 4)))");
+    assert_eq!(ast("i /= 4;"), "(expr (= i (/ i 4)))");
+    assert_eq!(ast("i += j += 1;"), "(expr (= i (+ i (= j (+ j 1)))))");
+}
+
+#[test]
+fn shifts_bind_looser_than_arithmetic_and_bitwise_tighter_than_equality() {
+    assert_eq!(ast("1 + 2 << 1;"), "(expr (<< (+ 1 2) 1))");
+    assert_eq!(ast("1 | 2 == 3;"), "(expr (== (| 1 2) 3))");
+    assert_eq!(ast("1 | 2 ^ 3 & 4;"), "(expr (| 1 (^ 2 (& 3 4))))");
+    assert_eq!(ast("~-x;"), "(expr (~ (- x)))");
+}
+
+#[test]
+fn chained_comparisons_are_rejected() {
+    assert_eq!(
+        compile_errors("print 1 < 2 < 3;"),
+        ["Chained comparison is not allowed; use parentheses or `and`"]
+    );
+    assert_eq!(compile_errors("print 1 < 2 >= 3;").len(), 1);
+}
+
+#[test]
+fn comparisons_joined_by_and_or_parentheses_parse() {
+    assert_eq!(
+        ast("(1 < 2) and (2 < 3);"),
+        "(expr (and (group (< 1 2)) (group (< 2 3))))"
+    );
+    assert_eq!(ast("(1 < 2) < 3;"), "(expr (< (group (< 1 2)) 3))");
+}
+
+#[test]
+fn dangling_else_binds_to_the_nearest_if() {
+    assert_eq!(
+        ast("if (a) if (b) x; else y;"),
+        "(if (group a)\n  (if (group b)\n    (expr x)\n    (expr y)))"
+    );
+}
+
+#[test]
+fn else_if_chains_lean_right() {
+    assert_eq!(
+        ast("if (a) x; else if (b) y; else z;"),
+        "(if (group a)\n  (expr x)\n  (if (group b)\n    (expr y)\n    (expr z)))"
+    );
+}
+
+#[test]
+fn increments_and_decrements_in_both_fixities() {
+    assert_eq!(ast("x++;"), "(expr (post++ x))");
+    assert_eq!(ast("--x;"), "(expr (pre-- x))");
+    assert_eq!(ast("print -x++;"), "(print (- (post++ x)))");
+}
+
+#[test]
+fn only_variables_can_be_incremented() {
+    for source in ["1++;", "++a.b;", "(x)--;"] {
+        assert_eq!(
+            compile_errors(source),
+            ["Invalid assignment target"],
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn switch_with_cases_and_a_default() {
+    assert_eq!(
+        ast("switch (x) { case 1: print 1; default: print 2; }"),
+        "(switch x\n  (case 1\n    (print 1))\n  (default\n    (print 2)))"
+    );
+}
+
+#[test]
+fn switch_default_has_to_come_last() {
+    let errors = compile_errors("switch (x) { default: print 1; case 2: print 2; }");
+    assert_eq!(errors.first().map(String::as_str), Some("Expect '}'"));
+}
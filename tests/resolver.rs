@@ -0,0 +1,265 @@
+mod common;
+
+use common::{compile_errors, output, run, warnings};
+use rlox::LoxError;
+
+#[test]
+fn an_initializer_cant_be_a_getter() {
+    assert_eq!(
+        compile_errors("class A { init { this.x = 1; } }"),
+        ["An initializer can't be a getter"]
+    );
+}
+
+#[test]
+fn reading_a_local_in_its_own_initializer() {
+    assert_eq!(
+        compile_errors("var a = 1; { var a = a; }"),
+        ["Can't read local variable in its own initializer"]
+    );
+}
+
+#[test]
+fn incrementing_a_local_in_its_own_initializer() {
+    for source in [
+        "{ var a = a++; }",
+        "{ var a = --a; }",
+        "var a = 1; { var a = 1 + a--; }",
+    ] {
+        assert_eq!(
+            compile_errors(source),
+            ["Can't read local variable in its own initializer"],
+            "{source}"
+        );
+    }
+    assert_eq!(
+        output("{ var a = 1; var b = a++; print b; print a; }"),
+        "1\n2\n"
+    );
+}
+
+#[test]
+fn globals_can_be_initialized_from_themselves() {
+    assert_eq!(
+        output("var a = \"outer\"; var a = a + \"!\"; print a;"),
+        "outer!\n"
+    );
+}
+
+#[test]
+fn declaring_a_name_twice_in_one_local_scope() {
+    let source = "{ var x = 1;\n var x = 2; }";
+    assert_eq!(
+        compile_errors(source),
+        ["Already a variable with this name in this scope"]
+    );
+    let Err(LoxError::Compile(diagnostics)) = run(source).1 else {
+        panic!("source should fail to compile");
+    };
+    assert_eq!(
+        (diagnostics[0].span.line, diagnostics[0].span.column),
+        (2, 6)
+    );
+}
+
+#[test]
+fn nested_scopes_can_shadow_a_name() {
+    assert_eq!(
+        output("{ var x = 1; { var x = 2; print x; } print x; }"),
+        "2\n1\n"
+    );
+}
+
+#[test]
+fn globals_can_be_declared_again() {
+    assert_eq!(output("var x = 1; var x = 2; print x;"), "2\n");
+}
+
+#[test]
+fn assigning_to_a_variable_many_scopes_out() {
+    let blocks = "{ ".repeat(10) + "x = \"set\"; " + &"} ".repeat(10);
+    assert_eq!(output(&format!("{{ var x; {blocks} print x; }}")), "set\n");
+}
+
+#[test]
+fn shadowing_locals_get_their_own_slots() {
+    assert_eq!(
+        output("{ var a = 1; var b = 2; { var b = 3; var a = 4; print a + b; } print a + b; }"),
+        "7\n3\n"
+    );
+}
+
+#[test]
+fn closures_keep_the_slots_of_the_scope_they_captured() {
+    assert_eq!(
+        output(
+            "fun counter(start) {\n\
+               var n = start;\n\
+               fun next() { var step = 1; n = n + step; return n; }\n\
+               return next;\n\
+             }\n\
+             var a = counter(0);\n\
+             var b = counter(10);\n\
+             a(); print a(); print b(); print a();"
+        ),
+        "2\n11\n3\n"
+    );
+}
+
+#[test]
+fn a_closure_sees_the_variable_it_resolved_to_not_a_later_shadow() {
+    assert_eq!(
+        output(
+            "var a = \"global\";\n\
+             { fun show() { print a; } show(); var a = \"local\"; show(); print a; }"
+        ),
+        "global\nglobal\nlocal\n"
+    );
+}
+
+#[test]
+fn variables_declared_in_a_loop_body_start_fresh_each_iteration() {
+    assert_eq!(
+        output(
+            "var fs = [];\n\
+             for (var i = 0; i < 3; i = i + 1) {\n\
+               var j = i * 10;\n\
+               push(fs, fun () { return j; });\n\
+             }\n\
+             for (var i = 0; i < 3; i = i + 1) print fs[i]();"
+        ),
+        "0\n10\n20\n"
+    );
+}
+
+#[test]
+fn code_after_a_return_is_unreachable() {
+    assert_eq!(
+        warnings("fun f() {\n  return 1;\n  print 2;\n}"),
+        [("Code after 'return' is unreachable".to_string(), 2)]
+    );
+}
+
+#[test]
+fn unreachable_code_is_only_a_warning() {
+    assert_eq!(output("fun f() { return 1; print 2; } print f();"), "1\n");
+}
+
+#[test]
+fn a_return_as_the_last_statement_or_in_a_branch_is_fine() {
+    assert!(warnings("fun f() { print 1; return 1; }").is_empty());
+    assert!(warnings("fun f(x) { if (x) return 1; print 2; }").is_empty());
+    assert!(warnings("fun f(x) { if (x) { return 1; } else { return 2; } }").is_empty());
+}
+
+#[test]
+fn only_the_first_of_several_jumps_is_reported() {
+    assert_eq!(
+        warnings("while (true) { break; continue; print 1; }"),
+        [("Code after 'break' is unreachable".to_string(), 1)]
+    );
+}
+
+#[test]
+fn return_at_the_top_level() {
+    assert_eq!(
+        compile_errors("return 5;"),
+        ["Can't return from top-level code"]
+    );
+    assert_eq!(
+        compile_errors("{ if (true) return; }"),
+        ["Can't return from top-level code"]
+    );
+}
+
+#[test]
+fn return_inside_a_function_is_fine() {
+    assert_eq!(output("fun f() { return 1; } print f();"), "1\n");
+    assert_eq!(
+        output("fun f() { { if (true) { return 2; } } } print f();"),
+        "2\n"
+    );
+    assert_eq!(output("var f = fun () { return 3; }; print f();"), "3\n");
+}
+
+#[test]
+fn top_level_return_is_reported_at_its_keyword() {
+    let Err(LoxError::Compile(diagnostics)) = run("print 1;\n  return;").1 else {
+        panic!("a top-level return should not compile");
+    };
+    assert_eq!(
+        (diagnostics[0].span.line, diagnostics[0].span.column),
+        (2, 3)
+    );
+}
+
+#[test]
+fn this_at_the_top_level() {
+    assert_eq!(
+        compile_errors("print this;"),
+        ["Can't use 'this' outside of a class"]
+    );
+}
+
+#[test]
+fn this_in_a_function_that_is_not_a_method() {
+    assert_eq!(
+        compile_errors("fun f() { return this; }"),
+        ["Can't use 'this' outside of a class"]
+    );
+}
+
+#[test]
+fn this_in_a_method_and_in_a_function_nested_in_one() {
+    assert_eq!(
+        output(
+            "class A {\n\
+               m() { return this; }\n\
+               later() { return fun () { return this; }; }\n\
+             }\n\
+             var a = A(); print a.m() == a; print a.later()() == a;"
+        ),
+        "true\ntrue\n"
+    );
+}
+
+#[test]
+fn super_at_the_top_level_or_in_a_function() {
+    for source in ["print super.x;", "fun f() { super.m(); }"] {
+        assert_eq!(
+            compile_errors(source),
+            ["Can't use 'super' outside of a class"]
+        );
+    }
+}
+
+#[test]
+fn super_in_a_class_with_no_superclass() {
+    assert_eq!(
+        compile_errors("class A { m() { return super.m(); } }"),
+        ["Can't use 'super' in a class with no superclass"]
+    );
+}
+
+#[test]
+fn super_in_a_subclass_method_is_fine() {
+    assert_eq!(
+        output(
+            "class A { m() { return \"A\"; } }\n\
+             class B < A { m() { return \"B\" + super.m(); } }\n\
+             print B().m();"
+        ),
+        "BA\n"
+    );
+}
+
+#[test]
+fn super_misuse_is_reported_at_the_keyword() {
+    let Err(LoxError::Compile(diagnostics)) = run("class A {\n  m() { super.m(); }\n}").1 else {
+        panic!("super without a superclass should not compile");
+    };
+    assert_eq!(
+        (diagnostics[0].span.line, diagnostics[0].span.column),
+        (2, 9)
+    );
+}
@@ -0,0 +1,510 @@
+use std::time::{Duration, Instant};
+
+use rlox::diagnostic::Span;
+use rlox::scanner::token::Token;
+use rlox::scanner::token_type::TokenType;
+use rlox::scanner::{ScanError, Scanner};
+
+fn scan(source: &str) -> Vec<Token> {
+    Scanner::new(source.to_string())
+        .scan_tokens()
+        .expect("source should scan")
+}
+
+fn scan_errors(source: &str) -> Vec<ScanError> {
+    Scanner::new(source.to_string())
+        .scan_tokens()
+        .expect_err("source should fail to scan")
+}
+
+fn positions(tokens: &[Token]) -> Vec<(String, usize, usize)> {
+    tokens
+        .iter()
+        .map(|token| (token.lexeme(), token.line(), token.column()))
+        .collect()
+}
+
+fn types(tokens: &[Token]) -> Vec<String> {
+    tokens
+        .iter()
+        .map(|token| token.token_type().to_string())
+        .collect()
+}
+
+/// Fastest of a few runs, so a busy machine doesn't skew the comparison
+fn time_to_scan(source: &str) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            scan(source);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+#[test]
+fn columns_of_tokens_on_one_line() {
+    let tokens = scan("var answer = 42;");
+    assert_eq!(
+        positions(&tokens),
+        [
+            ("var".to_string(), 1, 1),
+            ("answer".to_string(), 1, 5),
+            ("=".to_string(), 1, 12),
+            ("42".to_string(), 1, 14),
+            (";".to_string(), 1, 16),
+            ("".to_string(), 1, 17),
+        ]
+    );
+}
+
+#[test]
+fn columns_restart_after_a_newline() {
+    let tokens = scan("print 1;\n  print 2;");
+    assert_eq!(
+        positions(&tokens[3..5]),
+        [("print".to_string(), 2, 3), ("2".to_string(), 2, 9)]
+    );
+}
+
+#[test]
+fn columns_after_a_multi_line_string_count_from_its_last_line() {
+    let tokens = scan("\"a\nbc\" + x");
+    assert_eq!(positions(&tokens[..1]), [("\"a\nbc\"".to_string(), 1, 1)]);
+    assert_eq!(
+        positions(&tokens[1..3]),
+        [("+".to_string(), 2, 5), ("x".to_string(), 2, 7)]
+    );
+}
+
+#[test]
+fn columns_count_chars_rather_than_bytes() {
+    let tokens = scan("\"héllo\" + 名前;");
+    assert_eq!(
+        positions(&tokens[1..4]),
+        [
+            ("+".to_string(), 1, 9),
+            ("名前".to_string(), 1, 11),
+            (";".to_string(), 1, 13),
+        ]
+    );
+}
+
+#[test]
+fn columns_stay_exact_on_a_long_line() {
+    let tokens = scan(&("x + ".repeat(100_000) + "x;"));
+    assert_eq!(tokens.len(), 200_003);
+    let last_x = &tokens[tokens.len() - 3];
+    assert_eq!((last_x.line(), last_x.column()), (1, 400_001));
+}
+
+// Timing depends on the machine's load, so this is only run on request with `--ignored`
+#[test]
+#[ignore = "timing-dependent, run with --ignored"]
+fn scanning_time_grows_linearly_with_a_long_line() {
+    let line = |tokens: usize| "x + ".repeat(tokens) + "x;";
+    let small = time_to_scan(&line(50_000));
+    let large = time_to_scan(&line(400_000));
+    // 8 times the input, so linear scanning takes about 8 times as long and quadratic about 64
+    assert!(
+        large < small * 24,
+        "scanning 8 times the input took {large:?} instead of about 8 times {small:?}"
+    );
+}
+
+#[test]
+fn tabs_are_whitespace() {
+    let tokens = scan("fun f() {\n\tprint 1;\n\t\treturn;\n}");
+    assert_eq!(
+        types(&tokens),
+        [
+            "Fun",
+            "Identifier",
+            "LeftParen",
+            "RightParen",
+            "LeftBrace",
+            "Print",
+            "Number(1)",
+            "Semicolon",
+            "Return",
+            "Semicolon",
+            "RightBrace",
+            "Eof",
+        ]
+    );
+    // A tab counts as one column
+    assert_eq!(positions(&tokens[5..6]), [("print".to_string(), 2, 2)]);
+}
+
+#[test]
+fn identifiers_can_start_with_t() {
+    let tokens = scan("t tab true this");
+    assert_eq!(
+        types(&tokens),
+        ["Identifier", "Identifier", "True", "This", "Eof"]
+    );
+    assert_eq!(tokens[0].lexeme(), "t");
+}
+
+#[test]
+fn mixed_whitespace_separates_tokens() {
+    let tokens = scan(" \t\r\n var \t x\r\n=\t1 ;");
+    assert_eq!(
+        types(&tokens),
+        [
+            "Var",
+            "Identifier",
+            "Equal",
+            "Number(1)",
+            "Semicolon",
+            "Eof"
+        ]
+    );
+}
+
+#[test]
+fn block_comment_on_one_line() {
+    let tokens = scan("print /* a comment */ 1;");
+    assert_eq!(types(&tokens), ["Print", "Number(1)", "Semicolon", "Eof"]);
+}
+
+#[test]
+fn block_comment_over_lines_counts_them() {
+    let tokens = scan("/* one\ntwo\nthree */ print\n1;");
+    assert_eq!(
+        positions(&tokens[..2]),
+        [("print".to_string(), 3, 10), ("1".to_string(), 4, 1)]
+    );
+}
+
+#[test]
+fn nested_block_comment_needs_every_close() {
+    let tokens = scan("/* outer /* inner */ still a comment */ nil");
+    assert_eq!(types(&tokens), ["Nil", "Eof"]);
+}
+
+#[test]
+fn unterminated_block_comment() {
+    let errors = scan_errors("print 1;\n/* open /* closed */\nprint 2;");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Unterminated block comment.");
+    // Reported where the comment starts
+    assert_eq!(errors[0].span.line, 2);
+    assert!(errors[0].unterminated);
+}
+
+#[test]
+fn every_lexical_error_is_returned() {
+    let errors = scan_errors("var a = 1;\nvar b = @;\nprint a;\nprint \"open");
+    let found: Vec<_> = errors
+        .iter()
+        .map(|error| (error.message.as_str(), error.span.line, error.span.column))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("Unexpected character.", 2, 9),
+            ("Unterminated string.", 4, 7)
+        ]
+    );
+}
+
+#[test]
+fn multi_byte_chars_in_strings_and_identifiers() {
+    let tokens = scan("var café = \"naïve 日本 🦀\"; print café;");
+    assert_eq!(
+        types(&tokens),
+        [
+            "Var",
+            "Identifier",
+            "Equal",
+            "String(naïve 日本 🦀)",
+            "Semicolon",
+            "Print",
+            "Identifier",
+            "Semicolon",
+            "Eof",
+        ]
+    );
+    assert_eq!(tokens[1].lexeme(), "café");
+    assert_eq!(tokens[3].lexeme(), "\"naïve 日本 🦀\"");
+    assert_eq!(tokens[6].lexeme(), "café");
+}
+
+#[test]
+fn multi_byte_char_right_before_a_number() {
+    let tokens = scan("é1 ü 2");
+    let lexemes: Vec<_> = tokens.iter().map(Token::lexeme).collect();
+    assert_eq!(lexemes, ["é1", "ü", "2", ""]);
+}
+
+#[test]
+fn numbers_in_scientific_notation() {
+    let tokens = scan("1e10 2.5e-3 6.02E23 1e+2");
+    assert_eq!(
+        types(&tokens),
+        [
+            "Number(10000000000)",
+            "Number(0.0025)",
+            "Number(602000000000000000000000)",
+            "Number(100)",
+            "Eof",
+        ]
+    );
+}
+
+#[test]
+fn exponent_without_digits() {
+    let errors = scan_errors("print 1e;");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Expect digits in exponent.");
+    assert_eq!(errors[0].span.column, 7);
+}
+
+#[test]
+fn exponent_must_follow_a_digit() {
+    // As with `1.foo`, the dot after `1` is a property access
+    let tokens = scan("1.e5");
+    assert_eq!(types(&tokens), ["Number(1)", "Dot", "Identifier", "Eof"]);
+}
+
+#[test]
+fn tokens_expose_their_type_lexeme_and_line() {
+    let tokens = scan("\n\"hi\" 12.5");
+    let string = &tokens[0];
+    assert_eq!(string.token_type(), TokenType::String("hi".into()));
+    assert_eq!(string.lexeme(), "\"hi\"");
+    assert_eq!(string.line(), 2);
+    assert_eq!(tokens[1].token_type(), TokenType::Number(12.5));
+}
+
+#[test]
+fn iterating_token_by_token() {
+    let mut scanner = Scanner::new("print 1;".to_string());
+    let mut next = || scanner.next().map(|scanned| scanned.unwrap().token_type());
+    assert_eq!(next(), Some(TokenType::Print));
+    assert_eq!(next(), Some(TokenType::Number(1.0)));
+    assert_eq!(next(), Some(TokenType::Semicolon));
+    assert_eq!(next(), Some(TokenType::Eof));
+    assert_eq!(next(), None);
+    assert_eq!(next(), None);
+}
+
+#[test]
+fn empty_source_yields_only_eof() {
+    let mut scanner = Scanner::new(String::new());
+    assert!(matches!(scanner.next(), Some(Ok(token)) if token.token_type() == TokenType::Eof));
+    assert!(scanner.next().is_none());
+}
+
+#[test]
+fn iterator_yields_errors_in_place_and_carries_on() {
+    let scanned: Vec<_> = Scanner::new("1 @ 2".to_string()).collect();
+    assert_eq!(scanned.len(), 4);
+    assert!(scanned[1].is_err());
+    assert!(matches!(&scanned[3], Ok(token) if token.token_type() == TokenType::Eof));
+}
+
+#[test]
+fn collecting_the_iterator_into_a_vec() {
+    let source = "var x = \"s\"; x = x + 1;";
+    let tokens: Result<Vec<Token>, ScanError> = Scanner::new(source.to_string()).collect();
+    assert_eq!(types(&tokens.unwrap()), types(&scan(source)));
+}
+
+#[test]
+fn scan_tokens_moves_out_exactly_what_was_scanned() {
+    let source = "fun f(a) {\n  return a * 2;\n}\nprint f(21);";
+    let iterated: Vec<Token> = Scanner::new(source.to_string())
+        .map(Result::unwrap)
+        .collect();
+    let scanned = scan(source);
+    assert_eq!(scanned, iterated);
+    assert_eq!(
+        scanned
+            .iter()
+            .filter(|t| t.token_type() == TokenType::Eof)
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn unterminated_string_is_reported_where_it_opens() {
+    let errors = scan_errors("print 1;\nprint \"open\nstill\nin the\nstring");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Unterminated string.");
+    assert_eq!((errors[0].span.line, errors[0].span.column), (2, 7));
+}
+
+#[test]
+fn decimal_point_after_a_multi_byte_char_is_looked_ahead_correctly() {
+    let tokens = scan("\"日本\"12.5 é 3.25 ü.5");
+    assert_eq!(
+        types(&tokens),
+        [
+            "String(日本)",
+            "Number(12.5)",
+            "Identifier",
+            "Number(3.25)",
+            "Identifier",
+            "Dot",
+            "Number(5)",
+            "Eof",
+        ]
+    );
+}
+
+#[test]
+fn identifiers_with_accented_and_non_latin_letters() {
+    let tokens = scan("café naïve Ωmega 変数 x٣");
+    let lexemes: Vec<_> = tokens.iter().map(Token::lexeme).collect();
+    assert_eq!(lexemes, ["café", "naïve", "Ωmega", "変数", "x٣", ""]);
+    assert!(tokens[..5]
+        .iter()
+        .all(|token| token.token_type() == TokenType::Identifier));
+}
+
+#[test]
+fn identifiers_keep_combining_marks_and_connector_punctuation() {
+    // "café" spelled with an "e" followed by a combining acute accent
+    let decomposed = "cafe\u{301}";
+    let tokens = scan(&format!("var {decomposed} = 1; a\u{203f}b;"));
+    assert_eq!(tokens[1].token_type(), TokenType::Identifier);
+    assert_eq!(tokens[1].lexeme(), decomposed);
+    assert_eq!(tokens[5].lexeme(), "a\u{203f}b");
+}
+
+#[test]
+fn only_xid_characters_continue_an_identifier() {
+    // A superscript digit is alphanumeric but not XID_Continue
+    let errors = scan_errors("x²");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Unexpected character.");
+    // Nor can a combining mark start an identifier
+    assert_eq!(scan_errors("\u{301}a").len(), 1);
+}
+
+#[test]
+fn identifiers_can_start_with_an_underscore() {
+    let tokens = scan("_ _private __x1");
+    assert_eq!(
+        types(&tokens),
+        ["Identifier", "Identifier", "Identifier", "Eof"]
+    );
+}
+
+#[test]
+fn a_digit_cannot_start_an_identifier() {
+    let tokens = scan("1abc");
+    assert_eq!(types(&tokens), ["Number(1)", "Identifier", "Eof"]);
+    assert_eq!(tokens[1].lexeme(), "abc");
+}
+
+#[test]
+fn keywords_are_ascii_only() {
+    let tokens = scan("ｖａｒ var");
+    assert_eq!(types(&tokens), ["Identifier", "Var", "Eof"]);
+}
+
+#[test]
+fn underscores_separate_digits() {
+    let tokens = scan("1_000 3.141_592 1_000_000 1e1_0");
+    assert_eq!(
+        types(&tokens),
+        [
+            "Number(1000)",
+            "Number(3.141592)",
+            "Number(1000000)",
+            "Number(10000000000)",
+            "Eof",
+        ]
+    );
+    assert_eq!(tokens[0].lexeme(), "1_000");
+}
+
+#[test]
+fn separated_numbers_equal_their_plain_spelling() {
+    assert_eq!(
+        scan("12_345.678_9")[0].token_type(),
+        scan("12345.6789")[0].token_type()
+    );
+}
+
+#[test]
+fn misplaced_underscores_in_numbers() {
+    for source in [
+        "1__0", "_1", "_1_000", "__1", "1_", "1_.0", "1.5_", "1.5__1", "1e_5",
+    ] {
+        let errors = scan_errors(source);
+        assert_eq!(errors.len(), 1, "{source:?}");
+        assert_eq!(errors[0].message, "Misplaced '_' in number.", "{source:?}");
+    }
+}
+
+#[test]
+fn a_leading_underscore_followed_by_a_letter_makes_an_identifier() {
+    for source in ["_", "_x", "__init", "_1abc", "_x_1"] {
+        assert_eq!(types(&scan(source)), ["Identifier", "Eof"], "{source:?}");
+    }
+}
+
+#[test]
+fn byte_ranges_slice_each_lexeme_out_of_the_source() {
+    let source = "var café = \"日本\" + 1.5;\n// ü\nprint café;";
+    for token in scan(source) {
+        assert_eq!(source[token.span().byte_range()], token.lexeme());
+    }
+}
+
+#[test]
+fn byte_ranges_count_bytes_rather_than_chars() {
+    let tokens = scan("\"é\" x");
+    assert_eq!(tokens[0].span().byte_range(), 0..4);
+    assert_eq!(tokens[1].span().byte_range(), 5..6);
+    assert_eq!(tokens[2].span().byte_range(), 6..6);
+}
+
+#[test]
+fn a_span_has_both_character_and_byte_positions() {
+    let tokens = scan("\"日本\" café");
+    assert_eq!(
+        tokens[1].span(),
+        Span {
+            line: 1,
+            column: 6,
+            len: 4,
+            start: 9,
+            byte_len: 5,
+        }
+    );
+}
+
+#[test]
+fn scan_error_spans_cover_the_offending_character() {
+    let errors = scan_errors("é # ü");
+    assert_eq!(errors[0].span.byte_range(), 3..4);
+    let source = "é ü§";
+    let errors = scan_errors(source);
+    assert_eq!(&source[errors[0].span.byte_range()], "§");
+}
+
+#[test]
+fn doubled_plus_and_minus_scan_as_one_token() {
+    assert_eq!(
+        types(&scan("i++ --i +-+ ---")),
+        [
+            "Identifier",
+            "PlusPlus",
+            "MinusMinus",
+            "Identifier",
+            "Plus",
+            "Minus",
+            "Plus",
+            "MinusMinus",
+            "Minus",
+            "Eof"
+        ]
+    );
+}
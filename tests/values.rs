@@ -0,0 +1,71 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn whole_numbers_print_without_a_decimal_point() {
+    assert_eq!(
+        output("print 1; print 100.0; print -0.0; print 1e3;"),
+        "1\n100\n-0\n1000\n"
+    );
+}
+
+#[test]
+fn fractions_print_with_default_float_formatting() {
+    assert_eq!(output("print 1.5; print 10 / 4;"), "1.5\n2.5\n");
+    assert_eq!(output("print 1 / 3;"), "0.3333333333333333\n");
+}
+
+/// One value of every kind, with two different bools, numbers, strings and functions
+/// so that values of the same kind are also compared
+const DISTINCT_VALUES: &str = "\
+    class A {}\n\
+    fun f() {}\n\
+    var values = [nil, true, false, 0, 1, \"\", \"1\", f, fun () {}, clock, A, A(), [], {}];\n";
+
+#[test]
+fn each_value_only_equals_itself() {
+    // Number of elements in `values`
+    let count = 14;
+    let mut source = DISTINCT_VALUES.to_string();
+    let mut expected = String::new();
+    for i in 0..count {
+        for j in 0..count {
+            source +=
+                &format!("print values[{i}] == values[{j}]; print values[{i}] != values[{j}];\n");
+            expected += &format!("{}\n{}\n", i == j, i != j);
+        }
+    }
+    assert_eq!(output(&source), expected);
+}
+
+#[test]
+fn numbers_strings_and_bools_compare_by_value() {
+    assert_eq!(
+        output(
+            "print 1 == 1.0; print -0 == 0; print \"a\" + \"b\" == \"ab\"; print !true == false;"
+        ),
+        "true\ntrue\ntrue\ntrue\n"
+    );
+}
+
+#[test]
+fn functions_classes_instances_lists_and_maps_compare_by_identity() {
+    assert_eq!(
+        output(
+            "class A {} fun f() {}\n\
+             var l = [1]; var m = {\"k\": 1}; var a = A(); var g = f;\n\
+             print l == l; print [1] == [1]; print m == m; print {} == {};\n\
+             print a == a; print A() == A(); print g == f; print A == A;"
+        ),
+        "true\nfalse\ntrue\nfalse\ntrue\nfalse\ntrue\ntrue\n"
+    );
+}
+
+#[test]
+fn equality_across_types_is_false_rather_than_an_error() {
+    assert_eq!(
+        output("print 1 == \"1\"; print nil == false; print 0 == false; print \"\" == nil;"),
+        "false\nfalse\nfalse\nfalse\n"
+    );
+}